@@ -2,8 +2,17 @@ pub mod attribute;
 pub mod common;
 pub mod config;
 pub mod data;
+pub mod dialogue;
 pub mod entity;
+pub mod event;
 pub mod inventory;
+pub mod journal;
 pub mod location;
+pub mod loot;
+pub mod map;
 pub mod quest;
+pub mod recipe;
+pub mod road;
+pub mod script;
+pub mod template;
 pub mod time;
\ No newline at end of file
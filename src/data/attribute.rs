@@ -20,7 +20,8 @@ impl Named for Skill {
 pub struct Class {
     pub name: String,
     pub description: String,
-    pub proficiency: Vec<String>
+    pub proficiency: Vec<String>,
+    pub capacity: Option<f64>
 }
 
 
@@ -1,15 +1,21 @@
+extern crate rand;
 extern crate serde;
 
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::ops::{AddAssign, SubAssign};
 
+use rand::distributions::{WeightedIndex, Distribution};
 use serde::{Deserialize, Serialize};
 
 use super::super::input::controller::InputController;
 
 use super::data::GameData;
-use super::entity::{Player, PlayerStatus, PlayerCombatData, EntityInstance};
+use super::entity::PlayerStatus;
+use super::inventory::ItemInstance;
+use super::journal::LogText;
+use super::template;
+use super::time::Hours;
 
 pub trait Named {
     fn name(&self) -> String;
@@ -63,7 +69,9 @@ impl SubAssign<usize> for Range {
 #[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum InteractionLine {
+    #[serde(deserialize_with = "template::checked")]
     Action(String),
+    #[serde(deserialize_with = "template::checked")]
     Dialogue(String)
 }
 
@@ -79,9 +87,13 @@ impl Display for InteractionLine {
 }
 
 impl InteractionLine {
-    pub fn all(v: &Vec<InteractionLine>) -> String {
+    pub fn render(&self, game: &GameData) -> String {
+        template::render(&self.to_string(), game)
+    }
+
+    pub fn all(v: &[InteractionLine], game: &GameData) -> String {
         v.iter()
-            .map(|t| format!("{}", t))
+            .map(|t| t.render(game))
             .collect::<Vec<String>>()
             .join("\n\n")
     }
@@ -92,10 +104,33 @@ impl InteractionLine {
 pub enum Reward {
     Currency(f64),
     Items(HashMap<String, isize>),
+    /// Gives specific weapons or armor, such as a named or enchanted one.
+    Gear(Vec<ItemInstance>),
     Quests(Vec<String>),
     Marks(Vec<String>),
-    Logs(Vec<String>),
-    Complete(Vec<String>)
+    Logs(Vec<LogText>),
+    Complete(Vec<String>),
+    Loot(String),
+    Health(isize),
+    Reputation(HashMap<String, isize>),
+    /// Changes reputation wherever the player is.
+    LocalReputation(isize),
+    Effects(HashMap<String, usize>),
+    Teleport(String),
+    AdvanceTime(usize),
+    Skills(Vec<String>),
+    RemoveMarks(Vec<String>),
+    Fail(Vec<String>),
+    StartCombat(HashMap<String, usize>),
+    Chance(Vec<RewardChance>),
+    Script(String),
+    Titles(Vec<String>)
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RewardChance {
+    pub weight: u8,
+    pub rewards: Vec<Reward>
 }
 
 pub fn apply_vec(v: &Vec<String>, p: &mut Option<Vec<String>>) {
@@ -114,33 +149,176 @@ pub fn apply_vec(v: &Vec<String>, p: &mut Option<Vec<String>>) {
 }
 
 impl Reward {
-    pub fn apply(&self, player: &mut Player) {
+    pub fn apply(&self, game: &mut GameData) {
         use Reward::*;
 
+        let player = &mut game.global.player;
+
         match self {
             Currency(n) => { 
                 let _ = player.inventory.currency.add(*n, None); 
             }
             Items(m) => {
                 for i in m {
-                    player.inventory.add(i.0, *i.1);
+                    if let Err(e) = game.give(i.0, *i.1) {
+                        println!("{}", e);
+                    }
+                }
+
+                if m.values().any(|n| *n > 0) {
+                    game.check_load();
+                }
+            }
+            Gear(v) => {
+                for i in v {
+                    if let Err(e) = game.give_instance(i.clone()) {
+                        println!("{}", e);
+                    }
+                }
+
+                game.check_load();
+            }
+            Quests(v) => {
+                for q in v {
+                    player.quests.assign(q, &game.global.time);
                 }
             }
-            Quests(v) => apply_vec(v, &mut player.quests.assigned),
             Marks(v) => apply_vec(v, &mut player.stats.marks),
-            Logs(v) => apply_vec(v, &mut player.stats.log),
+            Logs(v) => {
+                for l in v {
+                    let entry = l.entry(game);
+                    game.write_journal(entry);
+                }
+            }
             Complete(v) => {
                 for q in v {
-                    player.quests.complete(q);
+                    game.complete_quest(q);
+                }
+            }
+            Loot(t) => {
+                let drops = game.loot_tables.get(t).unwrap().roll(game);
+                game.collect(&drops);
+            }
+            Health(n) => {
+                let health = &mut player.vitality.health;
+
+                *health += *n;
+                health.value = health.value.min(health.max);
+            }
+            Reputation(m) => {
+                for (l, n) in m {
+                    game.change_reputation(l, *n);
+                }
+            }
+            Effects(m) => {
+                for (e, n) in m {
+                    player.vitality.add_effect(e, *n);
+                }
+            }
+            LocalReputation(n) => {
+                let location = player.location.clone();
+                game.change_reputation(&location, *n);
+            }
+            Teleport(l) => {
+                player.status = PlayerStatus::Location;
+                game.arrive(l);
+            }
+            AdvanceTime(n) => {
+                game.pass_time(*n);
+                println!("{}", game.global.time.display(game));
+            }
+            Skills(v) => {
+                for s in v {
+                    if !player.skills.contains(s) {
+                        player.skills.push(s.clone());
+                    }
+                }
+            }
+            RemoveMarks(v) => {
+                if let Some(marks) = &mut player.stats.marks {
+                    marks.retain(|m| !v.contains(m));
+
+                    if marks.is_empty() {
+                        player.stats.marks = None;
+                    }
+                }
+            }
+            Fail(v) => {
+                for q in v {
+                    game.fail_quest(q);
+                }
+            }
+            StartCombat(m) => game.engage(m),
+            Chance(v) => {
+                let weights = v.iter()
+                    .map(|c| c.weight)
+                    .collect::<Vec<u8>>();
+
+                // Nothing happens if every weight is zero.
+                if let Ok(dist) = WeightedIndex::new(&weights) {
+                    let chosen = &v[dist.sample(&mut rand::thread_rng())];
+                    Self::apply_all(&chosen.rewards, game);
+                }
+            }
+            Script(s) => {
+                let (_, rewards) = game.scripts.run(s, game);
+                Self::apply_all(&rewards, game);
+            }
+            Titles(v) => {
+                for t in v {
+                    if !player.stats.titles.iter().flatten().any(|x| x == t) {
+                        println!("You've been granted the title of {}.", t);
+                    }
+                }
+
+                apply_vec(v, &mut player.stats.titles);
+            }
+        }
+    }
+
+    pub fn apply_all(v: &[Reward], game: &mut GameData) {
+        for r in v {
+            r.apply(game);
+        }
+    }
+
+    /// Checks that the loot tables, locations and entities this reward names exist.
+    /// `owner` describes where the reward comes from, for the error message.
+    pub fn validate(&self, owner: &str, game: &GameData) -> Result<(), String> {
+        use Reward::*;
+
+        match self {
+            Loot(t) if !game.loot_tables.contains_key(t) => {
+                Err(format!("{} drops from a loot table '{}' that doesn't exist", owner, t))
+            }
+            Teleport(l) if !game.locations.contains_key(l) => {
+                Err(format!("{} teleports to a location '{}' that doesn't exist", owner, l))
+            }
+            StartCombat(m) => validate_engage(m, owner, game),
+            Chance(v) => {
+                for c in v {
+                    Self::validate_all(&c.rewards, owner, game)?;
                 }
+
+                Ok(())
             }
+            _ => Ok(())
         }
     }
 
-    pub fn apply_all(v: &Vec<Reward>, player: &mut Player) {
+    pub fn validate_all(v: &[Reward], owner: &str, game: &GameData) -> Result<(), String> {
         for r in v {
-            r.apply(player);
+            r.validate(owner, game)?;
         }
+
+        Ok(())
+    }
+}
+
+fn validate_engage(m: &HashMap<String, usize>, owner: &str, game: &GameData) -> Result<(), String> {
+    match m.keys().find(|e| !game.entities.contains_key(*e)) {
+        Some(e) => Err(format!("{} starts a fight with an entity '{}' that doesn't exist", owner, e)),
+        None => Ok(())
     }
 }
 
@@ -159,7 +337,16 @@ pub struct DynamicInteraction {
 #[derive(Debug, Deserialize, Clone)]
 pub struct CombatInteraction {
     pub lines: Vec<InteractionLine>,
-    pub engage: HashMap<String, usize>
+    pub engage: HashMap<String, usize>,
+    /// Whether the player is the one starting this fight, such as by choosing to attack someone.
+    #[serde(default)]
+    pub brawl: bool
+}
+
+impl CombatInteraction {
+    pub fn validate(&self, owner: &str, game: &GameData) -> Result<(), String> {
+        validate_engage(&self.engage, owner, game)
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -174,20 +361,44 @@ pub struct DynamicChoice {
 pub enum InteractionType {
     Static(StaticInteraction),
     Dynamic(DynamicInteraction),
-    Combat(CombatInteraction)
+    Combat(CombatInteraction),
+    Dialogue(String),
+    Script(String)
 }
 
 impl InteractionType {
-    pub fn interact(&self, game: &mut GameData, input: &mut InputController) -> Option<String> {
+    /// Checks that the dialogues, entities and reward targets this interaction names exist, including those behind any choice.
+    pub fn validate(&self, owner: &str, game: &GameData) -> Result<(), String> {
+        use InteractionType::*;
+
+        match self {
+            Static(i) => Reward::validate_all(i.rewards.as_deref().unwrap_or_default(), owner, game),
+            Dynamic(i) => {
+                for c in &i.choices {
+                    c.interaction.validate(owner, game)?;
+                }
+
+                Ok(())
+            }
+            Combat(i) => i.validate(owner, game),
+            Dialogue(d) if !game.dialogues.contains_key(d) => {
+                Err(format!("{} leads to a dialogue '{}' that doesn't exist", owner, d))
+            }
+            _ => Ok(())
+        }
+    }
+
+    /// Runs the interaction. When it's a character's, `speaker` is their ID, so dialogue they lead is remembered.
+    pub fn interact(&self, speaker: Option<&String>, game: &mut GameData, input: &mut InputController) -> Option<String> {
         use InteractionType::*;
 
         match self {
             Static(i) => {
                 if let Some(r) = &i.rewards {
-                    Reward::apply_all(r, &mut game.global.player);
+                    Reward::apply_all(r, game);
                 }
 
-                Some(InteractionLine::all(&i.lines))
+                Some(InteractionLine::all(&i.lines, game))
             }
             Dynamic(i) => {
                 // Since choices can be filtered, we need to make a list of valid ones.
@@ -206,36 +417,42 @@ impl InteractionType {
                     .map(|d| d.response.clone())
                     .collect::<Vec<String>>();
 
-                println!("{}", InteractionLine::all(&i.lines));
+                println!("{}", InteractionLine::all(&i.lines, game));
 
                 let response = input.choice("What will you say?", responses, "You left the conversation.");
 
                 match response {
                     Some(d) => {
                         let choice = &choices[d.1];
-                        choice.interaction.interact(game, input)
+                        choice.interaction.interact(speaker, game, input)
                     }
                     None => None
                 }
             }
             Combat(i) => {
-                let mut entities: Vec<EntityInstance> = Vec::new();
+                if i.brawl {
+                    game.brawl();
+                }
 
-                for (e, n) in &i.engage {
-                    let instance = EntityInstance::from(game.entities.get(e).unwrap(), e.clone());
+                game.engage(&i.engage);
+                
+                Some(InteractionLine::all(&i.lines, game))
+            }
+            Dialogue(d) => {
+                let dialogue = game.dialogues.get(d).unwrap().clone();
+                let visited = dialogue.run(game, input);
 
-                    for _ in 0..*n {
-                        entities.push(instance.clone());
-                    }
+                if let Some(id) = speaker {
+                    game.global.characters.entry(id.clone()).or_default().visit(visited);
                 }
 
-                let data = PlayerCombatData {
-                    entities
-                };
+                None
+            }
+            Script(s) => {
+                let (text, rewards) = game.scripts.run(s, game);
+                Reward::apply_all(&rewards, game);
 
-                game.global.player.status = PlayerStatus::Combat(data);
-                
-                Some(InteractionLine::all(&i.lines))
+                text.map(|t| template::render(&t, game))
             }
         }
     }
@@ -292,12 +509,59 @@ pub enum Condition {
     Reputation(HashMap<String, usize>),
     Defeated(HashMap<String, usize>),
     Completed(HashMap<String, bool>),
+    Failed(HashMap<String, bool>),
     Finished(HashMap<String, bool>),
     Assigned(HashMap<String, bool>),
-    Marks(HashMap<String, bool>)
+    Marks(HashMap<String, bool>),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+    Time(Hours),
+    Day(usize),
+    Location(String),
+    Status(String),
+    Class(String),
+    Skill(String),
+    Effect(String),
+    Equipped(String),
+    /// Talked to characters, by ID, at least this many times.
+    Talked(HashMap<String, usize>),
+    Titles(HashMap<String, bool>),
+    Season(String),
+    Month(String),
+    Weekday(String),
+    /// A script that returns whether the condition is met. Like marks, it isn't displayed.
+    Script(String)
 }
 
 impl Condition {
+    /// The items this condition asks for, including ones nested in `All` and in whichever `Any` branch is met.
+    pub fn items(&self, game: &GameData) -> HashMap<String, usize> {
+        use Condition::*;
+
+        match self {
+            Items(m) => m.clone(),
+            All(v) => Self::items_all(v, game),
+            Any(v) => v.iter()
+                .find(|c| c.check(game))
+                .map(|c| c.items(game))
+                .unwrap_or_default(),
+            _ => HashMap::new()
+        }
+    }
+
+    pub fn items_all(v: &[Condition], game: &GameData) -> HashMap<String, usize> {
+        let mut result = HashMap::new();
+
+        for c in v {
+            for (i, n) in c.items(game) {
+                *result.entry(i).or_insert(0) += n;
+            }
+        }
+
+        result
+    }
+
     pub fn check(&self, game: &GameData) -> bool {
         use Condition::*;
 
@@ -306,17 +570,43 @@ impl Condition {
         match self {
             Currency(n) => player.inventory.currency.value >= *n,
             Health(n) => player.vitality.health.value >= *n,
-            Items(m) => compare_map(m, &player.inventory.items),
+            Items(m) => compare_map(m, &player.inventory.counts()),
             Reputation(m) => compare_map(m, &player.stats.reputation),
             Defeated(m) => compare_map(m, &player.stats.defeated),
             Completed(m) => compare_map_bool(&m, player.quests.completed.as_ref()),
+            Failed(m) => compare_map_bool(m, player.quests.failed.as_ref()),
             Finished(m) => compare_map_bool(&m, game.finished_quests().as_ref()),
             Assigned(m) => compare_map_bool(&m, player.quests.assigned.as_ref()),
-            Marks(m) => compare_map_bool(&m, player.stats.marks.as_ref())
+            Marks(m) => compare_map_bool(&m, player.stats.marks.as_ref()),
+            All(v) => Self::check_all(v, game),
+            Any(v) => v.iter().any(|c| c.check(game)),
+            Not(c) => !c.check(game),
+            Time(h) => h.contains(&game.global.time),
+            Day(n) => game.global.time.day >= *n,
+            Location(l) => &player.location == l,
+            Status(s) => player.status.name() == s,
+            Class(c) => &player.class == c,
+            Skill(s) => player.skills.contains(s),
+            Effect(e) => player.vitality.effects.contains_key(e),
+            Equipped(i) => player.inventory.equipped.all().iter().any(|x| &x.base == i),
+            Talked(m) => m.iter().all(|(k, n)| match game.global.characters.get(k) {
+                Some(c) => c.talked >= *n,
+                None => *n == 0
+            }),
+            Titles(m) => compare_map_bool(m, player.stats.titles.as_ref()),
+            Season(s) => game.global.time.date(game)
+                .and_then(|d| d.month.season.as_ref())
+                .is_some_and(|x| x.eq_ignore_ascii_case(s)),
+            Month(m) => game.global.time.date(game)
+                .is_some_and(|d| d.month.name.eq_ignore_ascii_case(m)),
+            Weekday(w) => game.global.time.date(game)
+                .and_then(|d| d.weekday)
+                .is_some_and(|x| x.eq_ignore_ascii_case(w)),
+            Script(s) => game.scripts.check(s, game)
         }
     }
 
-    pub fn check_all(v: &Vec<Condition>, game: &GameData) -> bool {
+    pub fn check_all(v: &[Condition], game: &GameData) -> bool {
         v.iter()
             .all(|c| c.check(game))
     }
@@ -349,13 +639,65 @@ impl Condition {
                 format!("Defeat (todo)")
             },
             Completed(m) => display_quest_map(m, "Complete", game),
+            Failed(m) => display_quest_map(m, "Fail", game),
             Finished(m) => display_quest_map(m, "Finish", game),
             Assigned(m) => display_quest_map(m, "Be assigned", game),
-            Marks(_) => String::new()
+            Marks(_) => String::new(),
+            All(v) => format!("({})", Self::display_joined(v, " and ", game)),
+            Any(v) => format!("Either {}", Self::display_joined(v, " or ", game)),
+            Not(c) => match c.display(game) {
+                s if s.is_empty() => s,
+                s => format!("Not: {}", s)
+            },
+            Time(h) => format!("It's between {} and {}", Hours::display_hour(h.from), Hours::display_hour(h.to)),
+            Day(n) => format!("Reach day {}", n),
+            Location(l) => format!("Be in {}", game.locations.get(l).unwrap().name),
+            Status(s) => format!("Be {}", match s.as_str() {
+                "combat" => "in combat",
+                "house" => "inside a house",
+                "dungeon" => "exploring a dungeon",
+                "location" => "out and about",
+                _ => "idle"
+            }),
+            Class(c) => format!("Be a {}", game.classes.get(c).unwrap().name),
+            Skill(s) => format!("Know {}", game.skills.get(s).unwrap().name),
+            Effect(e) => format!("Be affected by {}", game.effects.get(e).unwrap().name),
+            Equipped(i) => format!("Have {} equipped", game.items.get(i).unwrap().name),
+            Talked(m) => {
+                format!("Talk to {}", m.iter()
+                    .map(|(k, v)| {
+                        let name = game.character(k).map_or(k, |c| &c.name);
+
+                        match v {
+                            1 => name.clone(),
+                            _ => format!("{} {} times", name, v)
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", "))
+            },
+            Titles(m) => {
+                m.iter()
+                    .map(|(k, v)| format!("{} the title of {}", if *v { "Hold" } else { "Don't hold" }, k))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            },
+            Season(s) => format!("It's {}", s),
+            Month(m) => format!("It's {}", m),
+            Weekday(w) => format!("It's a {}", w),
+            Script(_) => String::new()
         }
     }
 
-    pub fn display_all(v: &Vec<Condition>, game: &GameData) -> Vec<String> {
+    /// Displays several conditions as one, leaving out hidden ones.
+    pub fn display_joined(v: &[Condition], sep: &str, game: &GameData) -> String {
+        Self::display_all(v, game).into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>()
+            .join(sep)
+    }
+
+    pub fn display_all(v: &[Condition], game: &GameData) -> Vec<String> {
         v.iter()
             .map(|c| c.display(game))
             .collect::<Vec<String>>()
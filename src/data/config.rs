@@ -3,6 +3,8 @@ extern crate serde_yaml;
 
 use serde::Deserialize;
 
+use super::time::Calendar;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Metadata {
     pub title: String,
@@ -18,11 +20,101 @@ pub struct CurrencyData {
     pub dist_cost: usize
 }
 
+/// What happens when the player picks up more than they can carry.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    Refuse,
+    /// Items are still picked up, but walking takes this many times longer.
+    Encumber(f64)
+}
+
+/// How the dark affects travelers.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Night {
+    /// How many times longer walking takes at night.
+    pub slowdown: Option<f64>,
+    /// Added to the danger of every road walked at night.
+    pub danger: Option<u8>
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct World {
     pub name: String,
     pub description: String,
-    pub currency: CurrencyData
+    pub currency: CurrencyData,
+    /// The default carrying capacity, for classes that don't set their own.
+    pub capacity: Option<f64>,
+    pub overflow: Option<Overflow>,
+    pub night: Option<Night>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReputationTier {
+    pub name: String,
+    /// The lowest reputation in this tier.
+    pub min: usize
+}
+
+/// Reputation drifting back toward a baseline as time passes.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Decay {
+    /// How far reputation drifts each time.
+    pub rate: usize,
+    /// How many minutes pass between each drift.
+    pub every: usize,
+    /// What reputation drifts toward. Defaults to each location's entry reputation.
+    pub baseline: Option<usize>
+}
+
+/// How much reputation the player's actions are worth where they happen.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ReputationActions {
+    /// Completing a quest from a location.
+    pub quest: isize,
+    /// Picking a fight in a town, through a combat interaction marked as a brawl.
+    pub brawl: isize
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Reputation {
+    pub max: usize,
+    /// The entry reputation of locations that don't set their own.
+    pub default: usize,
+    pub tiers: Vec<ReputationTier>,
+    pub decay: Option<Decay>,
+    pub actions: ReputationActions
+}
+
+impl Default for Reputation {
+    fn default() -> Self {
+        let tiers = [("hostile", 0), ("suspicious", 10), ("indifferent", 25), ("esteemed", 60), ("benevolent", 85)].iter()
+            .map(|(name, min)| ReputationTier {
+                name: name.to_string(),
+                min: *min
+            })
+            .collect::<Vec<ReputationTier>>();
+
+        Self {
+            max: 100,
+            default: 50,
+            tiers,
+            decay: None,
+            actions: ReputationActions::default()
+        }
+    }
+}
+
+impl Reputation {
+    /// The name of the highest tier a reputation reaches.
+    pub fn tier(&self, n: usize) -> &str {
+        self.tiers.iter()
+            .filter(|t| t.min <= n)
+            .max_by_key(|t| t.min)
+            .map_or("unknown", |t| t.name.as_str())
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -30,5 +122,8 @@ pub struct Config {
     pub metadata: Metadata,
     pub prompt: String,
     pub exposition: String,
-    pub world: World
+    pub world: World,
+    pub calendar: Option<Calendar>,
+    #[serde(default)]
+    pub reputation: Reputation
 }
\ No newline at end of file
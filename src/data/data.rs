@@ -5,12 +5,19 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use super::attribute::{Class, Skill};
-use super::config::Config;
-use super::common::{InteractionLine, Named};
-use super::entity::{Entity, Player, PlayerStatus};
-use super::inventory::{Item, Currency, StatusEffect};
-use super::location::{Location, House};
-use super::quest::Quest;
+use super::config::{Config, Overflow};
+use super::dialogue::Dialogue;
+use super::common::{Condition, InteractionLine, Named, Reward};
+use super::event::{Event, EventState, Trigger};
+use super::entity::{Character, CharacterState, CombatOrigin, Entity, EntityInstance, Player, PlayerCombatData, PlayerStatus};
+use super::inventory::{Item, ItemInstance, ItemType, Currency, Inventory, StatusEffect};
+use super::journal::{JournalCategory, JournalEntry};
+use super::location::{self, Location, LocationType, House, HouseResident, Whereabouts, PlaceState, Room, RoomState, Shop, Ware};
+use super::loot::LootTable;
+use super::quest::{Quest, QuestCompletion};
+use super::recipe::Recipe;
+use super::road::Route;
+use super::script::Scripts;
 use super::time::GameTime;
 use super::super::input::controller::InputController;
 use super::super::fs::fs::Filesystem;
@@ -18,48 +25,161 @@ use super::super::fs::fs::Filesystem;
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GlobalData {
     pub player: Player,
-    pub time: GameTime
+    pub time: GameTime,
+    #[serde(default)]
+    pub characters: HashMap<String, CharacterState>,
+    #[serde(default)]
+    pub places: HashMap<String, PlaceState>,
+    #[serde(default)]
+    pub events: HashMap<String, EventState>
 }
 
 #[derive(Debug)]
 pub struct GameData {
     pub config: Config,
     pub classes: HashMap<String, Class>,
+    pub dialogues: HashMap<String, Dialogue>,
     pub effects: HashMap<String, StatusEffect>,
     pub entities: HashMap<String, Entity>,
+    pub events: HashMap<String, Event>,
     pub houses: HashMap<String, House>,
     pub items: HashMap<String, Item>,
     pub locations: HashMap<String, Location>,
+    pub loot_tables: HashMap<String, LootTable>,
     pub skills: HashMap<String, Skill>,
     pub quests: HashMap<String, Quest>,
-    pub global: GlobalData
+    pub recipes: HashMap<String, Recipe>,
+    pub scripts: Scripts,
+    pub global: GlobalData,
+    /// Finished quests the player has already been told about.
+    pub finished: Vec<String>
 }
 
 impl GameData {
     pub fn from(fs: &Filesystem) -> Result<Self, Box<dyn std::error::Error>> {
         let config = Filesystem::parse(fs.read("jage.yml")?)?;
         let classes = Filesystem::parse_map(fs.read_dir("classes")?)?;
+        let dialogues = Filesystem::parse_map(fs.read_optional_dir("dialogues")?)?;
         let effects = Filesystem::parse_map(fs.read_dir("effects")?)?;
         let entities = Filesystem::parse_map(fs.read_dir("entities")?)?;
+        let events = Filesystem::parse_map(fs.read_optional_dir("events")?)?;
         let houses = Filesystem::parse_map(fs.read_dir("houses")?)?;
         let items = Filesystem::parse_map(fs.read_dir("items")?)?;
         let locations = Filesystem::parse_map(fs.read_dir("locations")?)?;
+        let loot_tables = Filesystem::parse_map(fs.read_optional_dir("loot")?)?;
         let skills = Filesystem::parse_map(fs.read_dir("skills")?)?;
         let quests = Filesystem::parse_map(fs.read_dir("quests")?)?;
+        let recipes = Filesystem::parse_map(fs.read_optional_dir("recipes")?)?;
+        let scripts = Scripts::load(fs.read_optional_dir("scripts")?)?;
         let global = Filesystem::parse(fs.read("data/global.yml")?)?;
 
-        Ok(Self {
+        let mut game = Self {
             config,
             classes,
+            dialogues,
             effects,
             entities,
+            events,
             houses,
             items,
             locations,
+            loot_tables,
             skills,
             quests,
-            global
-        })
+            recipes,
+            scripts,
+            global,
+            finished: Vec::new()
+        };
+
+        game.validate()?;
+        game.global.player.inventory.fill(&game.items);
+        // Automatic quests are left out, so any that are already finished get completed on the first update.
+        game.finished = game.finished_quests().unwrap_or_default().into_iter()
+            .filter(|q| !matches!(game.quests.get(q).unwrap().completion, Some(QuestCompletion::Automatic)))
+            .collect::<Vec<String>>();
+
+        Ok(game)
+    }
+
+    /// Checks that content refers to things that exist, so mistakes are caught when the game loads.
+    fn validate(&self) -> Result<(), String> {
+        for (id, d) in &self.dialogues {
+            d.validate(id)?;
+
+            for n in d.nodes.values() {
+                Reward::validate_all(n.rewards.as_deref().unwrap_or_default(), &format!("Dialogue '{}'", id), self)?;
+            }
+        }
+
+        for c in self.characters() {
+            c.validate(self)?;
+        }
+
+        for (id, q) in &self.quests {
+            let owner = format!("Quest '{}'", id);
+            let stages = q.stages.iter().flatten().filter_map(|s| s.rewards.as_ref());
+
+            for v in stages.chain(q.rewards.iter()) {
+                Reward::validate_all(v, &owner, self)?;
+            }
+        }
+
+        for (id, e) in &self.events {
+            Reward::validate_all(e.rewards.as_deref().unwrap_or_default(), &format!("Event '{}'", id), self)?;
+        }
+
+        for (id, t) in &self.loot_tables {
+            t.validate(id, self)?;
+        }
+
+        for (id, e) in &self.entities {
+            if let Some(t) = e.loot.as_ref().filter(|t| !self.loot_tables.contains_key(*t)) {
+                return Err(format!("Entity '{}' drops from a loot table '{}' that doesn't exist", id, t));
+            }
+        }
+
+        for (id, l) in &self.locations {
+            let owner = format!("Location '{}'", id);
+
+            match &l.l_type {
+                LocationType::Bandit(b) => {
+                    b.encounter.validate(&owner, self)?;
+
+                    if let Some(t) = b.loot.as_ref().filter(|t| !self.loot_tables.contains_key(*t)) {
+                        return Err(format!("{} drops from a loot table '{}' that doesn't exist", owner, t));
+                    }
+                }
+                LocationType::Monument(m) => {
+                    let rewards = m.blessing.as_ref().and_then(|b| b.rewards.as_deref());
+                    Reward::validate_all(rewards.unwrap_or_default(), &owner, self)?;
+                }
+                LocationType::Dungeon(d) => {
+                    for r in d.rooms.values() {
+                        if let Some(c) = &r.encounter {
+                            c.validate(&owner, self)?;
+                        }
+
+                        Reward::validate_all(r.treasure.as_deref().unwrap_or_default(), &owner, self)?;
+                    }
+                }
+                _ => ()
+            }
+        }
+
+        for (id, l) in &self.locations {
+            if let Some(d) = l.dungeon() {
+                d.validate(id)?;
+            }
+
+            for r in l.roads.iter().flatten() {
+                if !self.locations.contains_key(&r.to) {
+                    return Err(format!("Location '{}' has a road to '{}', which doesn't exist", id, r.to));
+                }
+            }
+        }
+
+        Ok(())
     }
 
     pub fn location(&self) -> &Location {
@@ -78,7 +198,14 @@ impl GameData {
 
     pub fn travel(&mut self, id: &String, next: &Location, input: &mut InputController) {
         let current = self.location();
-        let data = current.travel_prompt(&next, self);
+        let route = Route::find(&self.global.player.location, id, self);
+
+        // Whether it's dark is decided when the player sets out.
+        let danger = match &self.config.world.night {
+            Some(n) if self.global.time.is_night() => n.danger.unwrap_or(0),
+            _ => 0
+        };
+        let data = current.travel_prompt(next, &route, self);
 
         let choices = vec!["Ride carriage", "Walk"].iter()
             .map(|s| String::from(*s))
@@ -96,26 +223,298 @@ impl GameData {
                         }
                     }
                     1 => {
-                        self.global.time.advance(data.2);
+                        self.pass_time(data.2);
+
+                        if self.encumbrance().is_some() {
+                            println!("Your load slows you down.");
+                        }
+
                         println!("You decided to walk.");
                     }
                     _ => ()
                 }
 
-                let entry = next.entry(id, &self.global.player.stats.reputation);
-                
-                if entry.2 {
-                    self.global.player.stats.reputation.insert(id.clone(), entry.1);
-                }
+                println!();
 
-                println!("\n{}\n{}", entry.0, self.global.time);
+                // Only walkers are out on the roads long enough to be caught.
+                // They have to fight their way through before they get where they're going.
+                if d.1 == 1 {
+                    if let Some((leg, encounter)) = route.ambush(danger) {
+                        println!("You were ambushed on the road to {}!", self.locations.get(&leg.to).unwrap().name);
+                        self.engage_from(encounter, Some(CombatOrigin::Road(id.clone())));
+                        return;
+                    }
+                }
 
-                self.global.player.location = id.clone();
+                self.arrive(id);
             },
             None => ()
         }
     }
 
+    /// Moves the player to a location, greeting them with their reputation there.
+    pub fn arrive(&mut self, id: &String) {
+        let next = self.locations.get(id).unwrap();
+        let entry = next.entry(id, self);
+        
+        if entry.2 {
+            self.global.player.stats.reputation.insert(id.clone(), entry.1);
+        }
+
+        println!("{}\n{}", entry.0, self.global.time.display(self));
+
+        self.global.player.location = id.clone();
+    }
+
+    /// Shifts the player's reputation at a location, starting from its entry reputation if they've never been.
+    pub fn change_reputation(&mut self, id: &String, n: isize) {
+        let location = self.locations.get(id).unwrap();
+        let current = location.reputation(id, self).0 as isize;
+        let value = (current + n).clamp(0, self.config.reputation.max as isize) as usize;
+
+        self.global.player.stats.reputation.insert(id.clone(), value);
+
+        println!("Your reputation in {} is now {}.", location.name, value);
+    }
+
+    /// Starts a fight with some number of each entity.
+    pub fn engage(&mut self, engage: &HashMap<String, usize>) {
+        self.engage_from(engage, None);
+    }
+
+    /// Costs the player reputation for starting a fight in a town.
+    pub fn brawl(&mut self) {
+        use LocationType::*;
+
+        let brawl = self.config.reputation.actions.brawl;
+
+        if brawl != 0 && matches!(self.location().l_type, Town(_) | City(_) | Capital(_)) {
+            self.change_reputation(&self.global.player.location.clone(), brawl);
+        }
+    }
+
+    pub fn engage_from(&mut self, engage: &HashMap<String, usize>, origin: Option<CombatOrigin>) {
+        let mut entities: Vec<EntityInstance> = Vec::new();
+
+        for (e, n) in engage {
+            let instance = EntityInstance::from(self.entities.get(e).unwrap(), e.clone());
+
+            for _ in 0..*n {
+                entities.push(instance.clone());
+            }
+        }
+
+        let data = PlayerCombatData {
+            entities,
+            origin
+        };
+
+        self.global.player.status = PlayerStatus::Combat(data);
+    }
+
+    /// The player's carrying capacity, if the world or their class sets one.
+    pub fn capacity(&self) -> Option<f64> {
+        match self.classes.get(&self.global.player.class) {
+            Some(Class { capacity: Some(n), .. }) => Some(*n),
+            _ => self.config.world.capacity
+        }
+    }
+
+    /// How much longer walking takes, if the player is carrying more than their capacity.
+    pub fn encumbrance(&self) -> Option<f64> {
+        let capacity = self.capacity()?;
+
+        match self.config.world.overflow {
+            Some(Overflow::Encumber(n)) if self.global.player.inventory.weight(self) > capacity => Some(n),
+            _ => None
+        }
+    }
+
+    /// Whether the player can take on some extra weight.
+    /// Always true if the world encumbers rather than refuses.
+    pub fn fits(&self, weight: f64) -> bool {
+        match self.capacity() {
+            Some(capacity) if self.global.player.inventory.weight(self) + weight > capacity => {
+                matches!(self.config.world.overflow, Some(Overflow::Encumber(_)))
+            }
+            _ => true
+        }
+    }
+
+    /// Warns the player if what they just picked up leaves them encumbered.
+    pub fn check_load(&self) {
+        if self.encumbrance().is_some() {
+            println!("You're carrying more than you can manage.");
+        }
+    }
+
+    /// Adds or removes items from the player's inventory, respecting their carrying capacity.
+    /// Errors if the items don't fit and the world refuses overflow.
+    pub fn give(&mut self, id: &String, n: isize) -> Result<(), String> {
+        if n > 0 {
+            let item = self.items.get(id).unwrap();
+
+            if !self.fits(item.weight() * n as f64) {
+                return Err(format!("You can't carry {} more {}.", n, item.name));
+            }
+        }
+
+        self.put(id, n);
+
+        Ok(())
+    }
+
+    /// Adds or removes items from the player's inventory, creating new instances where needed.
+    /// This skips capacity checks; see `GameData::give`.
+    pub fn put(&mut self, id: &String, n: isize) {
+        let item = self.items.get(id).unwrap();
+
+        if item.instanced() && n > 0 {
+            let instance = ItemInstance::from(item, id.clone());
+
+            for _ in 0..n {
+                self.global.player.inventory.instances.push(instance.clone());
+            }
+        } else {
+            self.global.player.inventory.add(id, n);
+        }
+    }
+
+    /// Adds a specific instance of an item to the player's inventory, respecting their carrying capacity.
+    pub fn give_instance(&mut self, mut instance: ItemInstance) -> Result<(), String> {
+        let item = self.items.get(&instance.base).unwrap();
+
+        if !self.fits(item.weight()) {
+            return Err(format!("You can't carry {}.", instance.display(self)));
+        }
+
+        instance.fill(item);
+        self.global.player.inventory.instances.push(instance);
+
+        Ok(())
+    }
+
+    /// Swaps a weapon or armor from the player's bag into its slot, putting back whatever was there.
+    pub fn equip(&mut self, input: &mut InputController) {
+        let gear = self.global.player.inventory.instances.iter()
+            .enumerate()
+            .filter(|(_, i)| self.items.get(&i.base).unwrap().equippable().is_some())
+            .map(|(n, i)| (n, i.display(self)))
+            .collect::<Vec<(usize, String)>>();
+
+        if gear.is_empty() {
+            println!("You don't have anything to equip.");
+            return;
+        }
+
+        let names = gear.iter()
+            .map(|d| d.1.clone())
+            .collect::<Vec<String>>();
+
+        let n = match input.choice("What will you equip?", names, "You decided not to equip anything.") {
+            Some(d) => gear[d.1].0,
+            None => return
+        };
+
+        let inventory = &mut self.global.player.inventory;
+        let instance = inventory.instances.remove(n);
+
+        let slot = match &self.items.get(&instance.base).unwrap().i_type {
+            ItemType::Weapon(_) => &mut inventory.equipped.weapon,
+            _ => &mut inventory.equipped.armor
+        };
+
+        if let Some(old) = slot.replace(instance.clone()) {
+            inventory.instances.push(old);
+        }
+
+        println!("You equipped your {}.", instance.display(self));
+    }
+
+    /// Moves the contents of another inventory, such as a defeated entity's, into the player's.
+    /// Returns the names of any items that didn't fit.
+    pub fn loot(&mut self, other: &Inventory) -> Vec<String> {
+        let _ = self.global.player.inventory.currency.add(other.currency.value, None);
+
+        let mut left = Vec::new();
+
+        for (i, v) in &other.items {
+            if self.give(i, *v as isize).is_err() {
+                left.push(self.items.get(i).unwrap().name.clone());
+            }
+        }
+
+        for i in &other.instances {
+            if self.give_instance(i.clone()).is_err() {
+                left.push(i.display(self));
+            }
+        }
+
+        self.check_load();
+
+        left
+    }
+
+    pub fn craft(&mut self, input: &mut InputController) {
+        let recipes = self.recipes.iter()
+            .filter(|(_, r)| r.craftable(self))
+            .map(|(k, r)| (k.clone(), r.display(self)))
+            .collect::<Vec<(String, String)>>();
+
+        if recipes.is_empty() {
+            println!("You can't craft anything right now.");
+            return;
+        }
+
+        let names = recipes.iter()
+            .map(|d| d.1.clone())
+            .collect::<Vec<String>>();
+
+        if let Some(d) = input.choice("What will you craft?", names, "You decided not to craft anything.") {
+            let recipe = self.recipes.get(&recipes[d.1].0).unwrap().clone();
+
+            if !self.fits(recipe.weight(self)) {
+                println!("You can't carry what you'd make.");
+                return;
+            }
+
+            for (i, n) in &recipe.inputs {
+                self.global.player.inventory.take(i, *n);
+            }
+
+            for (i, n) in &recipe.outputs {
+                self.put(i, *n as isize);
+            }
+
+            self.pass_time(recipe.time);
+
+            println!("You crafted {}.\n{}", Recipe::display_items(&recipe.outputs, self), self.global.time.display(self));
+
+            self.check_load();
+        }
+    }
+
+    /// Picks up a set of drops, telling the player what they found and what they had to leave.
+    pub fn collect(&mut self, drops: &Inventory) {
+        let mut found = drops.display(self).unwrap_or_default();
+
+        if drops.currency.value > 0.0 {
+            found.push(Currency::display(drops.currency.value, self));
+        }
+
+        if found.is_empty() {
+            return;
+        }
+
+        println!("You found {}.", found.join(", "));
+
+        let left = self.loot(drops);
+
+        if !left.is_empty() {
+            println!("You had to leave behind: {}.", left.join(", "));
+        }
+    }
+
     pub fn house(&self) -> Option<&House> {
         if let PlayerStatus::House(h) = &self.global.player.status {
             Some(self.houses.get(h).unwrap())
@@ -124,7 +523,253 @@ impl GameData {
         }
     }
 
+    /// Everyone in a house right now, including visitors from other houses.
+    pub fn residents(&self, house: &String) -> Vec<HouseResident> {
+        self.find_residents(|w| matches!(w, Whereabouts::House(x) if x == house))
+    }
+
+    /// Everyone out and about in a location right now, rather than in one of its houses.
+    pub fn locals(&self, location: &String) -> Vec<HouseResident> {
+        self.find_residents(|w| matches!(w, Whereabouts::Location(x) if x == location))
+    }
+
+    fn find_residents<F: Fn(&Whereabouts) -> bool>(&self, f: F) -> Vec<HouseResident> {
+        let mut ids = self.houses.keys().collect::<Vec<&String>>();
+        ids.sort();
+
+        ids.into_iter()
+            .flat_map(|h| self.houses.get(h).unwrap().residents.iter().map(move |r| (h, r)))
+            .filter(|(h, r)| f(&r.whereabouts(h, self)))
+            .map(|(_, r)| r.clone())
+            .collect::<Vec<HouseResident>>()
+    }
+
+    /// Lets the player pick someone to talk to.
+    pub fn talk(&mut self, residents: Vec<HouseResident>, input: &mut InputController) {
+        let names = residents.iter()
+            .map(|r| r.name().clone())
+            .collect::<Vec<String>>();
+
+        if names.is_empty() {
+            println!("There's nobody here right now.");
+            return;
+        }
+
+        if let Some(d) = input.choice("Who will you talk to?", names, "You decided not to talk to anyone.") {
+            if let Some(s) = residents[d.1].talk(self, input) {
+                println!("{}", s);
+            }
+        }
+    }
+
+    /// Every character living in a house or ruling from a palace.
+    pub fn characters(&self) -> impl Iterator<Item = &Character> {
+        let residents = self.houses.values()
+            .flat_map(|h| h.residents.iter())
+            .filter_map(|r| match r {
+                HouseResident::Character(c) => Some(c),
+                _ => None
+            });
+
+        let rulers = self.locations.values()
+            .filter_map(|l| l.palace())
+            .map(|p| p.ruler.as_ref());
+
+        residents.chain(rulers)
+    }
+
+    /// Finds a character by ID, whether they live in a house or rule from a palace.
+    pub fn character(&self, id: &str) -> Option<&Character> {
+        self.characters().find(|c| c.id() == id)
+    }
+
+    /// The location a house is in.
+    pub fn house_location(&self, house: &String) -> Option<&Location> {
+        self.locations.values()
+            .find(|l| l.houses(self).is_some_and(|v| v.iter().any(|h| &h.0 == house)))
+    }
+
+    /// Where a character the player has met is right now.
+    pub fn whereabouts(&self, name: &String) -> String {
+        let found = self.houses.iter()
+            .flat_map(|(h, house)| house.residents.iter().map(move |r| (h, r)))
+            .find_map(|(h, r)| match r {
+                HouseResident::Character(c) if c.id() == *name || c.name.eq_ignore_ascii_case(name) => Some((h, c)),
+                _ => None
+            });
+
+        let met = |c: &Character| self.global.characters.contains_key(&c.id());
+
+        // Rulers don't keep schedules; they're always at court.
+        let ruler = self.locations.values()
+            .filter_map(|l| Some((l, l.palace()?)))
+            .find(|(_, p)| p.ruler.id() == *name || p.ruler.name.eq_ignore_ascii_case(name));
+
+        if let Some((l, p)) = ruler.filter(|(_, p)| met(&p.ruler)) {
+            return format!("{} holds court at the {} in {}.", p.ruler.name, p.name, l.name);
+        }
+
+        let (home, character) = match found {
+            Some(f) if met(f.1) => f,
+            _ => return format!("You haven't met anyone called {}.", name)
+        };
+
+        let resident = HouseResident::Character(character.clone());
+
+        match resident.whereabouts(home, self) {
+            Whereabouts::House(h) => {
+                let house = &self.houses.get(&h).unwrap().name;
+
+                match self.house_location(&h) {
+                    Some(l) => format!("{} is at the {} in {}.", character.name, house, l.name),
+                    None => format!("{} is at the {}.", character.name, house)
+                }
+            }
+            Whereabouts::Location(l) => format!("{} is out and about in {}.", character.name, self.locations.get(&l).unwrap().name),
+            Whereabouts::Away => format!("Nobody knows where {} is right now.", character.name)
+        }
+    }
+
+    /// Asks for an audience with the ruler of the current capital.
+    pub fn audience(&mut self, input: &mut InputController) {
+        let id = self.global.player.location.clone();
+        let palace = self.location().palace().unwrap().clone();
+
+        if !palace.admits(&id, self) {
+            match &palace.refused {
+                Some(v) => println!("{}", InteractionLine::all(v, self)),
+                None => println!("The guards at the {} turn you away.", palace.name)
+            }
+
+            return;
+        }
+
+        println!("You're granted an audience at the {}.\n", palace.name);
+
+        self.turn_in(Some(&palace.ruler.id()));
+
+        if let Some(s) = palace.ruler.talk(self, input) {
+            println!("{}", s);
+        }
+    }
+
+    /// Visits whatever the current location has to offer.
     pub fn visit(&mut self, input: &mut InputController) {
+        use LocationType::*;
+
+        let id = self.global.player.location.clone();
+
+        match self.location().l_type.clone() {
+            Town(_) | City(_) | Capital(_) => self.visit_house(input),
+            Bandit(b) => {
+                if self.place(&id).cleared {
+                    if let Some(v) = &b.cleared {
+                        println!("{}", InteractionLine::all(v, self));
+                    }
+
+                    println!("There's no one left here.");
+                    return;
+                }
+
+                println!("{}", InteractionLine::all(&b.encounter.lines, self));
+                self.engage_from(&b.encounter.engage, Some(CombatOrigin::Camp(id)));
+            }
+            Monument(m) => {
+                println!("{}", InteractionLine::all(&m.lore, self));
+
+                if let Some(b) = &m.blessing {
+                    if !self.place(&id).blessed {
+                        self.global.places.entry(id).or_default().blessed = true;
+
+                        println!("\n{}", InteractionLine::all(&b.lines, self));
+
+                        if let Some(r) = &b.rewards {
+                            Reward::apply_all(r, self);
+                        }
+                    }
+                }
+            }
+            Dungeon(d) => {
+                let open = match &d.conditions {
+                    Some(v) => Condition::check_all(v, self),
+                    None => true
+                };
+
+                if !open {
+                    match &d.locked {
+                        Some(v) => println!("{}", InteractionLine::all(v, self)),
+                        None => println!("The way in is barred.")
+                    }
+
+                    return;
+                }
+
+                println!("{}\n", InteractionLine::all(&d.entry, self));
+                self.enter_room(&d.start);
+            }
+            Trader(t) => self.trade(&id, &t.name, &t.greeting, &t.wares, input)
+        }
+    }
+
+    /// What the player has done at a location, or a fresh state if they haven't done anything.
+    pub fn place(&self, id: &String) -> PlaceState {
+        self.global.places.get(id).cloned().unwrap_or_default()
+    }
+
+    fn trade(&mut self, id: &String, name: &str, greeting: &[InteractionLine], wares: &[Ware], input: &mut InputController) {
+        println!("{}\n", InteractionLine::all(greeting, self));
+
+        let day = self.global.time.day;
+        let state = self.place(id);
+
+        let names = wares.iter()
+            .map(|w| {
+                let item = &self.items.get(&w.item).unwrap().name;
+
+                match w.stock {
+                    Some(n) => format!("{} - {} ({} left)", item, Currency::display(w.price, self), n.saturating_sub(state.bought(&w.item, day))),
+                    None => format!("{} - {}", item, Currency::display(w.price, self))
+                }
+            })
+            .collect::<Vec<String>>();
+
+        let ware = match input.choice(&format!("What will you buy from {}?", name), names, "You decided not to buy anything.") {
+            Some(d) => &wares[d.1],
+            None => return
+        };
+
+        if let Some(n) = ware.stock {
+            if state.bought(&ware.item, day) >= n {
+                println!("{} is out of those for today.", name);
+                return;
+            }
+        }
+
+        // Check the player can carry it before taking their money.
+        if let Err(e) = self.give(&ware.item, 1) {
+            println!("{}", e);
+            return;
+        }
+
+        match self.global.player.inventory.currency.add(-ware.price, Some(&self.config.world.currency.plural)) {
+            Ok(_) => {
+                self.global.places.entry(id.clone()).or_default().buy(&ware.item, day);
+
+                println!("You bought a {}. You now have {}.",
+                    self.items.get(&ware.item).unwrap().name,
+                    Currency::display(self.global.player.inventory.currency.value, self)
+                );
+
+                self.check_load();
+            }
+            Err(e) => {
+                self.put(&ware.item, -1);
+                println!("{}.", e);
+            }
+        }
+    }
+
+    fn visit_house(&mut self, input: &mut InputController) {
         let houses = self.location().houses(self).unwrap();
 
         let names = houses.iter()
@@ -134,24 +779,88 @@ impl GameData {
         match input.choice("Which house will you visit?", names, "You decided not to visit anyone.") {
             Some(d) => {
                 let house = &houses[d.1];
+
+                if !self.houses.get(&house.0).unwrap().open(self) {
+                    println!("The {} is locked for the night.", house.1);
+                    return;
+                }
+
                 self.global.player.status = PlayerStatus::House(house.0.clone());
 
                 println!("You entered the {}.\n\n{}", 
                     house.1,
-                    InteractionLine::all(&self.house().unwrap().entry)
+                    InteractionLine::all(self.house().unwrap().entry(self), self)
                 );
+
+                self.turn_in(None);
             }
             None => ()
         }
     }
 
+    /// Returns the quests from `ids` the player can currently take on.
+    /// Quests that are already assigned or completed, or whose prerequisites aren't met, are left out.
+    pub fn available_quests(&self, ids: &[String]) -> Vec<(String, String)> {
+        let quests = &self.global.player.quests;
+
+        ids.iter()
+            .filter(|q| !quests.is_assigned(q) && !quests.is_completed(q) && !quests.is_failed(q))
+            .map(|q| (q.clone(), self.quests.get(q).unwrap()))
+            .filter(|d| d.1.available(self) && !d.1.failed(&d.0, self))
+            .map(|d| (d.0, d.1.name.clone()))
+            .collect::<Vec<(String, String)>>()
+    }
+
+    pub fn board(&mut self, input: &mut InputController) {
+        let tavern = match self.location().tavern() {
+            Some(t) => t.clone(),
+            None => return
+        };
+
+        if let Err(e) = location::opening(&tavern.name, &tavern.hours, self) {
+            println!("{}", e);
+            return;
+        }
+
+        let quests = self.available_quests(&tavern.quests);
+
+        if quests.is_empty() {
+            println!("There's nothing on the quest board at the {}.", tavern.name);
+            return;
+        }
+
+        let names = quests.iter()
+            .map(|d| d.1.clone())
+            .collect::<Vec<String>>();
+
+        let text = format!("You look over the quest board at the {}.", tavern.name);
+
+        if let Some(d) = input.choice(text.as_str(), names, "You stepped away from the board.") {
+            let id = &quests[d.1].0;
+            let quest = self.quests.get(id).unwrap();
+
+            println!("\n{}", quest.display(id, self));
+
+            let choices = vec![String::from("Accept"), String::from("Leave it")];
+
+            match input.choice("Will you take on this quest?", choices, "You left the notice on the board.") {
+                Some((_, 0)) => {
+                    self.global.player.quests.assign(id, &self.global.time);
+                    println!("You accepted '{}'.", quest.name);
+                }
+                Some(_) => println!("You left the notice on the board."),
+                None => ()
+            }
+        }
+    }
+
     pub fn finished_quests(&self) -> Option<Vec<String>> {
         match &self.global.player.quests.assigned.clone() {
             Some(v) => {
                 Some(
                     v.iter()
                         .map(|q| q.clone())
-                        .filter(|q| self.quests.get(q).unwrap().check(self))
+                        .filter(|q| self.quests.get(q).unwrap().check(q, self))
                         .collect::<Vec<String>>()
                 )
             },
@@ -184,7 +893,7 @@ impl GameData {
                         .map(|d| format!("- {}{}",
                             d.0.name,
                             if show_completed {
-                                if d.0.check(self) {
+                                if d.0.check(&d.1, self) {
                                     String::from(" ✔")
                                 } else {
                                     String::new()
@@ -202,15 +911,299 @@ impl GameData {
     }
 
     pub fn quest_book(&mut self) -> String {
-        format!("Assigned:\n{}\n\nCompleted:\n{}",
+        format!("Assigned:\n{}\n\nCompleted:\n{}\n\nFailed:\n{}",
             self.display_quests(&self.quest_list(&self.global.player.quests.assigned), true),
-            self.display_quests(&self.quest_list(&self.global.player.quests.completed), false)
+            self.display_quests(&self.quest_list(&self.global.player.quests.completed), false),
+            self.display_quests(&self.quest_list(&self.global.player.quests.failed), false)
         )
     }
 
+    /// Advances the clock. Any events that come due along the way fire at the end of the command,
+    /// once the player has got wherever they're going.
+    pub fn pass_time(&mut self, n: usize) {
+        let before = self.global.time.total();
+
+        self.global.time.advance(n);
+        self.decay_reputation(before);
+    }
+
+    /// Drifts reputation toward its baseline once for every interval passed since `before`.
+    fn decay_reputation(&mut self, before: usize) {
+        let decay = match &self.config.reputation.decay {
+            Some(d) if d.every > 0 => d.clone(),
+            _ => return
+        };
+
+        let steps = self.global.time.total() / decay.every - before / decay.every;
+
+        if steps == 0 {
+            return;
+        }
+
+        let ids = self.global.player.stats.reputation.keys()
+            .cloned()
+            .collect::<Vec<String>>();
+
+        for id in ids {
+            let baseline = match (decay.baseline, self.locations.get(&id)) {
+                (Some(n), _) => n,
+                (None, Some(l)) => l.baseline(self),
+                (None, None) => continue
+            };
+
+            let value = self.global.player.stats.reputation.get_mut(&id).unwrap();
+            let drift = steps * decay.rate;
+
+            *value = if *value > baseline {
+                value.saturating_sub(drift).max(baseline)
+            } else {
+                (*value + drift).min(baseline)
+            };
+        }
+    }
+
+    /// Fires every event that's come due, in order of ID.
+    /// Repeating events apply their rewards once for each time they came due since they were last checked.
+    pub fn update_events(&mut self) {
+        let mut ids = self.events.keys()
+            .cloned()
+            .collect::<Vec<String>>();
+
+        ids.sort();
+
+        for id in ids {
+            let event = self.events.get(&id).unwrap().clone();
+            let state = self.global.events.get(&id).cloned();
+            let fired = state.as_ref().map_or(0, |s| s.fired);
+            let due = event.trigger.due(&self.global.time);
+
+            if due <= fired {
+                continue;
+            }
+
+            // An event that's never been checked only fires once, rather than for every time it would have before.
+            let times = if state.is_some() { due - fired } else { 1 };
+
+            let met = match &event.conditions {
+                Some(v) => Condition::check_all(v, self),
+                None => true
+            };
+
+            // Conditional events wait for their conditions; timed ones just miss this time.
+            if !met {
+                if let Trigger::When = event.trigger {
+                    continue;
+                }
+            }
+
+            // Timed events that miss their conditions still use up this occurrence.
+            self.global.events.insert(id, EventState {
+                fired: due,
+                last: Some(self.global.time.clone())
+            });
+
+            if !met {
+                continue;
+            }
+
+            if let Some(v) = &event.lines {
+                println!("\n{}", InteractionLine::all(v, self));
+            }
+
+            if let Some(r) = &event.rewards {
+                for _ in 0..times {
+                    Reward::apply_all(r, self);
+                }
+            }
+        }
+    }
+
+    /// Moves assigned quests through any stages whose conditions are now met.
+    pub fn update_quests(&mut self) {
+        let assigned = self.global.player.quests.assigned.clone().unwrap_or_default();
+
+        for id in &assigned {
+            let quest = self.quests.get(id).unwrap().clone();
+
+            if quest.failed(id, self) {
+                self.fail_quest(id);
+                continue;
+            }
+
+            // Each stage advances at most once per update, so branches that loop back
+            // wait for the next command instead of spinning and repeating their rewards.
+            let mut advanced: Vec<String> = Vec::new();
+
+            while let Some(stage) = quest.stage(id, self) {
+                if advanced.contains(&stage.id) || !Condition::check_all(&stage.conditions, self) {
+                    break;
+                }
+
+                advanced.push(stage.id.clone());
+
+                let mut progress = quest.progress(id, self).unwrap();
+                let next = quest.next(stage, self);
+
+                if !progress.completed.contains(&stage.id) {
+                    progress.completed.push(stage.id.clone());
+                }
+                progress.stage = next.clone();
+
+                self.global.player.quests.progress.insert(id.clone(), progress);
+
+                if let Some(r) = &stage.rewards {
+                    Reward::apply_all(r, self);
+                }
+
+                match next.and_then(|s| quest.get_stage(&s)) {
+                    Some(s) => {
+                        println!("Quest updated: {}\n{}", quest.name, s.journal);
+
+                        self.write_journal(JournalEntry {
+                            text: s.journal.clone(),
+                            time: self.global.time.clone(),
+                            location: self.global.player.location.clone(),
+                            category: JournalCategory::Quest,
+                            quest: Some(id.clone())
+                        });
+                    }
+                    None => println!("Quest updated: {}", quest.name)
+                }
+            }
+        }
+
+        self.notify_quests();
+    }
+
+    /// Tells the player about quests that became finished or stopped being finished,
+    /// completing any that complete automatically.
+    pub fn notify_quests(&mut self) {
+        let finished = self.finished_quests().unwrap_or_default();
+
+        for id in &finished {
+            if self.finished.contains(id) {
+                continue;
+            }
+
+            let quest = self.quests.get(id).unwrap();
+
+            match &quest.completion {
+                Some(QuestCompletion::Automatic) => self.complete_quest(id),
+                Some(QuestCompletion::TurnIn(t)) => println!("Quest ready: {}\n{}", quest.name, t.display(self)),
+                None => println!("Quest ready: {}", quest.name)
+            }
+        }
+
+        for id in &self.finished {
+            if !finished.contains(id) && self.global.player.quests.is_assigned(id) {
+                println!("Quest no longer ready: {}", self.quests.get(id).unwrap().name);
+            }
+        }
+
+        self.finished = self.finished_quests().unwrap_or_default();
+    }
+
+    /// Completes any finished quests that can be turned in to this house or character.
+    pub fn turn_in(&mut self, character: Option<&String>) {
+        let house = match &self.global.player.status {
+            PlayerStatus::House(h) => Some(h.clone()),
+            _ => None
+        };
+
+        // Outside of a house, there's nobody to turn quests in to but the one being spoken with.
+        if house.is_none() && character.is_none() {
+            return;
+        }
+
+        for id in self.finished_quests().unwrap_or_default() {
+            if let Some(QuestCompletion::TurnIn(t)) = &self.quests.get(&id).unwrap().completion {
+                if t.matches(house.as_ref(), character) {
+                    self.complete_quest(&id);
+                }
+            }
+        }
+    }
+
+    pub fn fail_quest(&mut self, id: &String) {
+        if !self.global.player.quests.is_assigned(id) {
+            return;
+        }
+
+        self.global.player.quests.fail(id);
+
+        println!("Quest failed: {}", self.quests.get(id).unwrap().name);
+    }
+
+    pub fn abandon(&mut self, id: &String, input: &mut InputController) {
+        let quest = self.quests.get(id).unwrap();
+
+        if !self.global.player.quests.is_assigned(id) {
+            println!("You aren't on that quest.");
+            return;
+        }
+
+        let text = format!("Abandon '{}'? You won't be able to take it on again.", quest.name);
+        let choices = vec![String::from("Abandon"), String::from("Keep going")];
+
+        if let Some((_, 0)) = input.choice(text.as_str(), choices, "You kept the quest.") {
+            self.fail_quest(id);
+        }
+    }
+
+    /// Adds an entry to the player's journal, unless the same text was already written down.
+    pub fn write_journal(&mut self, entry: JournalEntry) {
+        let log = self.global.player.stats.log.get_or_insert_with(Vec::new);
+
+        if log.iter().any(|e| e.text == entry.text) {
+            return;
+        }
+
+        log.push(entry);
+
+        println!("Your journal has been updated.");
+    }
+
+    /// Completes a quest, granting its final rewards.
+    pub fn complete_quest(&mut self, id: &String) {
+        if self.global.player.quests.is_completed(id) {
+            return;
+        }
+
+        let quest = self.quests.get(id).unwrap().clone();
+
+        if quest.consume {
+            for (i, n) in Condition::items_all(&quest.requirements, self) {
+                self.global.player.inventory.take(&i, n);
+            }
+        }
+
+        self.global.player.quests.complete(id);
+
+        println!("Quest completed: {}", quest.name);
+
+        let gain = self.config.reputation.actions.quest;
+
+        if gain != 0 {
+            let mut places = self.locations.iter()
+                .filter(|(_, l)| l.offers(id))
+                .map(|(k, _)| k.clone())
+                .collect::<Vec<String>>();
+
+            places.sort();
+
+            for l in places {
+                self.change_reputation(&l, gain);
+            }
+        }
+
+        if let Some(r) = &quest.rewards {
+            Reward::apply_all(r, self);
+        }
+    }
+
     pub fn cycle_combat(&mut self) {
         for (_, v) in &mut self.global.player.vitality.effects {
-            *v -= 1;
+            *v = v.saturating_sub(1);
         }
         
         self.global.player.vitality.effects.retain(|_, v| *v != 0);
@@ -224,7 +1217,314 @@ impl GameData {
             for e in &c.entities {
                 let entity = self.entities.get(&e.base).unwrap();
                 entity.choose_attack().apply(&mut self.global.player);
+
+                if let Some(armor) = &mut self.global.player.inventory.equipped.armor {
+                    if armor.wear() {
+                        println!("Your {} broke.", self.items.get(&armor.base).unwrap().name);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn attack(&mut self, input: &mut InputController) {
+        let entities = match &self.global.player.status {
+            PlayerStatus::Combat(c) => c.entities.clone(),
+            _ => return
+        };
+
+        let names = entities.iter()
+            .map(|e| format!("{} ({} health)", self.entities.get(&e.base).unwrap().name, e.health.value))
+            .collect::<Vec<String>>();
+
+        let target = if entities.len() == 1 {
+            0
+        } else {
+            match input.choice("What will you attack?", names, "You held back.") {
+                Some(d) => d.1,
+                None => return
+            }
+        };
+
+        let strength = match &self.global.player.inventory.equipped.weapon {
+            Some(weapon) => weapon.strength(self).max(1),
+            None => 1
+        };
+
+        if let Some(weapon) = &mut self.global.player.inventory.equipped.weapon {
+            if weapon.wear() {
+                println!("Your {} broke.", self.items.get(&weapon.base).unwrap().name);
+            }
+        }
+
+        let base = entities[target].base.clone();
+        let entity = self.entities.get(&base).unwrap();
+
+        if let PlayerStatus::Combat(c) = &mut self.global.player.status {
+            c.entities[target].health -= strength;
+
+            println!("You hit the {} for {} damage.", entity.name, strength);
+
+            if c.entities[target].health.value > 0 {
+                return;
+            }
+
+            c.entities.remove(target);
+        }
+
+        println!("You defeated the {}.", entity.name);
+
+        *self.global.player.stats.defeated.entry(base).or_insert(0) += 1;
+
+        let drops = entity.drops(self);
+        self.collect(&drops);
+
+        if let PlayerStatus::Combat(c) = &self.global.player.status {
+            if c.entities.is_empty() {
+                let origin = c.origin.clone();
+
+                self.global.player.status = PlayerStatus::Location;
+                println!("You won the fight.");
+
+                if let Some(o) = origin {
+                    self.win(o);
+                }
             }
         }
     }
+
+    /// Settles whatever a won fight was about.
+    fn win(&mut self, origin: CombatOrigin) {
+        match origin {
+            CombatOrigin::Camp(id) => {
+                self.global.places.entry(id.clone()).or_default().cleared = true;
+
+                let location = self.locations.get(&id).unwrap();
+                println!("You cleared out {}.", location.name);
+
+                if let LocationType::Bandit(b) = &location.l_type {
+                    if let Some(t) = b.loot.clone() {
+                        Reward::Loot(t).apply(self);
+                    }
+                }
+            }
+            CombatOrigin::Road(id) => {
+                println!("You continue on your way.\n");
+                self.arrive(&id);
+            }
+            CombatOrigin::Room(room) => {
+                self.room_state(&room).cleared = true;
+                self.global.player.status = PlayerStatus::Dungeon(room);
+
+                println!("The room is clear.");
+            }
+        }
+    }
+
+    /// The state of a room in the dungeon the player is in.
+    fn room_state(&mut self, room: &str) -> &mut RoomState {
+        self.global.places.entry(self.global.player.location.clone()).or_default()
+            .rooms.entry(room.to_string()).or_default()
+    }
+
+    pub fn room(&self) -> Option<(String, &Room)> {
+        let id = match &self.global.player.status {
+            PlayerStatus::Dungeon(r) => r.clone(),
+            _ => return None
+        };
+
+        let room = self.location().dungeon()?.rooms.get(&id)?;
+
+        Some((id, room))
+    }
+
+    pub fn look(&self) -> String {
+        let (_, room) = match self.room() {
+            Some(r) => r,
+            None => return String::new()
+        };
+
+        let mut exits = room.exits.keys()
+            .cloned()
+            .collect::<Vec<String>>();
+
+        exits.sort();
+
+        format!("{}\n\n{}\n\nExits: {}",
+            room.name,
+            InteractionLine::all(&room.description, self),
+            if exits.is_empty() { String::from("none") } else { exits.join(", ") }
+        )
+    }
+
+    /// Moves the player into a dungeon room, starting its fight if it hasn't been won yet.
+    pub fn enter_room(&mut self, id: &str) {
+        self.global.player.status = PlayerStatus::Dungeon(id.to_string());
+
+        println!("{}", self.look());
+
+        let room = match self.room() {
+            Some(r) => r.1.clone(),
+            None => return
+        };
+
+        if let Some(e) = &room.encounter {
+            if !self.room_state(id).cleared {
+                println!("\n{}", InteractionLine::all(&e.lines, self));
+                self.engage_from(&e.engage, Some(CombatOrigin::Room(id.to_string())));
+            }
+        }
+    }
+
+    pub fn go(&mut self, direction: &str) {
+        let (_, room) = match self.room() {
+            Some(r) => r,
+            None => return
+        };
+
+        let exit = match room.exits.iter().find(|(k, _)| k.to_lowercase() == direction.to_lowercase()) {
+            Some(e) => e.1.clone(),
+            None => {
+                println!("You can't go that way.");
+                return;
+            }
+        };
+
+        let open = match &exit.conditions {
+            Some(v) => Condition::check_all(v, self),
+            None => true
+        };
+
+        if !open {
+            match &exit.locked {
+                Some(v) => println!("{}", InteractionLine::all(v, self)),
+                None => println!("The way is locked.")
+            }
+
+            return;
+        }
+
+        self.enter_room(&exit.to);
+    }
+
+    pub fn search(&mut self) {
+        let (id, room) = match self.room() {
+            Some(r) => r,
+            None => return
+        };
+
+        let treasure = match &room.treasure {
+            Some(v) => v.clone(),
+            None => Vec::new()
+        };
+
+        let state = self.room_state(&id);
+
+        if treasure.is_empty() || state.looted {
+            println!("You find nothing of interest.");
+            return;
+        }
+
+        state.looted = true;
+
+        Reward::apply_all(&treasure, self);
+    }
+
+    pub fn repair(&mut self, input: &mut InputController) {
+        let shops = match self.location().shops() {
+            Some(v) => v.iter()
+                .filter(|s| s.repair.is_some())
+                .cloned()
+                .collect::<Vec<Shop>>(),
+            None => Vec::new()
+        };
+
+        let open = shops.iter()
+            .filter(|s| location::opening(&s.name, &s.hours, self).is_ok())
+            .cloned()
+            .collect::<Vec<Shop>>();
+
+        // Tell the player when they could come back, rather than that nobody does repairs.
+        if open.is_empty() {
+            if let Some(Err(e)) = shops.first().map(|s| location::opening(&s.name, &s.hours, self)) {
+                println!("{}", e);
+                return;
+            }
+        }
+
+        let shops = open;
+
+        let shop = match shops.len() {
+            0 => {
+                println!("Nobody here can repair your gear.");
+                return;
+            }
+            1 => &shops[0],
+            _ => {
+                let names = shops.iter()
+                    .map(|s| s.name.clone())
+                    .collect::<Vec<String>>();
+
+                match input.choice("Where will you go for repairs?", names, "You decided not to repair anything.") {
+                    Some(d) => &shops[d.1],
+                    None => return
+                }
+            }
+        };
+
+        let inventory = &self.global.player.inventory;
+        let rate = shop.repair.unwrap();
+
+        // Equipped items come first, then the rest of the bag. Indices past the
+        // equipped ones point into `Inventory.instances`.
+        let slots = [&inventory.equipped.weapon, &inventory.equipped.armor];
+
+        let damaged = slots.iter()
+            .enumerate()
+            .filter_map(|(n, i)| i.as_ref().map(|i| (n, i)))
+            .chain(inventory.instances.iter().enumerate().map(|(n, i)| (n + 2, i)))
+            .filter(|(_, i)| i.damaged())
+            .map(|(n, i)| {
+                let r = i.durability.as_ref().unwrap();
+                (n, i.display(self), (r.max - r.value) as f64 * rate)
+            })
+            .collect::<Vec<(usize, String, f64)>>();
+
+        if damaged.is_empty() {
+            println!("None of your gear needs repairing.");
+            return;
+        }
+
+        let names = damaged.iter()
+            .map(|d| format!("{} for {}", d.1, Currency::display(d.2, self)))
+            .collect::<Vec<String>>();
+
+        let text = format!("What will you have repaired at the {}?", shop.name);
+
+        if let Some(d) = input.choice(text.as_str(), names, "You decided not to repair anything.") {
+            let (n, _, cost) = &damaged[d.1];
+            let inventory = &mut self.global.player.inventory;
+
+            if let Err(e) = inventory.currency.add(-cost, Some(&self.config.world.currency.plural)) {
+                println!("{}", e);
+                return;
+            }
+
+            let instance = match n {
+                0 => inventory.equipped.weapon.as_mut(),
+                1 => inventory.equipped.armor.as_mut(),
+                _ => inventory.instances.get_mut(n - 2)
+            }.unwrap();
+
+            let durability = instance.durability.as_mut().unwrap();
+            durability.value = durability.max;
+
+            let repaired = instance.clone();
+
+            println!("Your {} has been repaired. You now have {}.",
+                repaired.display(self),
+                Currency::display(self.global.player.inventory.currency.value, self)
+            );
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,151 @@
+extern crate serde;
+
+use serde::Deserialize;
+
+use std::collections::HashMap;
+
+use super::common::{Condition, InteractionLine, Reward};
+use super::data::GameData;
+use super::entity::PlayerStatus;
+use super::super::input::controller::InputController;
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogueTarget {
+    Goto(String),
+    /// Returns to the node the player came from.
+    Back,
+    End
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DialogueChoice {
+    pub response: String,
+    #[serde(alias = "to")]
+    pub target: DialogueTarget,
+    pub conditions: Option<Vec<Condition>>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DialogueNode {
+    pub lines: Vec<InteractionLine>,
+    /// Choices leading here are hidden unless these are met.
+    pub conditions: Option<Vec<Condition>>,
+    pub rewards: Option<Vec<Reward>>,
+    pub choices: Option<Vec<DialogueChoice>>,
+    /// Where to go when there's nothing to choose. Ends the conversation if unset.
+    pub next: Option<DialogueTarget>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Dialogue {
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>
+}
+
+impl Dialogue {
+    /// Whether a choice can be picked, given its own conditions and those of the node it leads to.
+    pub fn available(&self, choice: &DialogueChoice, game: &GameData) -> bool {
+        let own = match &choice.conditions {
+            Some(v) => Condition::check_all(v, game),
+            None => true
+        };
+
+        let target = match &choice.target {
+            DialogueTarget::Goto(n) => match self.nodes.get(n).and_then(|n| n.conditions.as_ref()) {
+                Some(v) => Condition::check_all(v, game),
+                None => true
+            },
+            _ => true
+        };
+
+        own && target
+    }
+
+    /// Checks that the start and every node a choice or `next` leads to exist.
+    pub fn validate(&self, id: &str) -> Result<(), String> {
+        let targets = self.nodes.values()
+            .flat_map(|n| n.choices.iter().flatten().map(|c| &c.target).chain(n.next.iter()))
+            .filter_map(|t| match t {
+                DialogueTarget::Goto(n) => Some(n),
+                _ => None
+            });
+
+        for n in std::iter::once(&self.start).chain(targets) {
+            if !self.nodes.contains_key(n) {
+                return Err(format!("Dialogue '{}' leads to a node '{}' that doesn't exist", id, n));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs the conversation until it ends, returning the nodes visited in order.
+    pub fn run(&self, game: &mut GameData, input: &mut InputController) -> Vec<String> {
+        let mut visited: Vec<String> = Vec::new();
+        let mut path: Vec<String> = Vec::new();
+        let mut current = self.start.clone();
+
+        // Nodes passed through without the player choosing anything. Coming back to one
+        // of these would go around in circles forever, so the conversation ends instead.
+        let mut chain: Vec<String> = Vec::new();
+
+        while let Some(node) = self.nodes.get(&current) {
+            println!("{}", InteractionLine::all(&node.lines, game));
+
+            // Looping back to a node shouldn't hand out its rewards twice.
+            if !visited.contains(&current) {
+                if let Some(r) = &node.rewards {
+                    Reward::apply_all(r, game);
+                }
+            }
+
+            visited.push(current.clone());
+
+            if let PlayerStatus::Combat(_) = game.global.player.status {
+                break;
+            }
+
+            let choices = node.choices.iter()
+                .flatten()
+                .filter(|c| self.available(c, game))
+                .collect::<Vec<&DialogueChoice>>();
+
+            let target = if choices.is_empty() {
+                chain.push(current.clone());
+                node.next.clone().unwrap_or(DialogueTarget::End)
+            } else {
+                chain.clear();
+
+                let responses = choices.iter()
+                    .map(|c| c.response.clone())
+                    .collect::<Vec<String>>();
+
+                match input.choice("What will you say?", responses, "You left the conversation.") {
+                    Some(d) => choices[d.1].target.clone(),
+                    None => DialogueTarget::End
+                }
+            };
+
+            println!();
+
+            match target {
+                DialogueTarget::Goto(n) => {
+                    path.push(current);
+                    current = n;
+                }
+                DialogueTarget::Back => match path.pop() {
+                    Some(n) => current = n,
+                    None => break
+                },
+                DialogueTarget::End => break
+            }
+
+            if chain.contains(&current) {
+                break;
+            }
+        }
+
+        visited
+    }
+}
@@ -8,6 +8,12 @@ use serde::{Deserialize, Serialize};
 
 use super::inventory::Inventory;
 use super::common::{InteractionType, Range};
+use super::data::GameData;
+use super::journal::{self, JournalEntry};
+use super::location::ScheduleEntry;
+use super::quest::QuestProgress;
+use super::time::GameTime;
+use super::super::input::controller::InputController;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct EntityAttack {
@@ -22,12 +28,8 @@ impl EntityAttack {
         player.vitality.health -= self.strength;
 
         if let Some(m) = &self.effects {
-            for (e, n) in m.clone() {
-                if player.vitality.effects.contains_key(&e) {
-                    *player.vitality.effects.get_mut(&e).unwrap() += n;
-                } else {
-                    player.vitality.effects.insert(e.clone(), n);
-                }
+            for (e, n) in m {
+                player.vitality.add_effect(e, *n);
             }
         }
     }
@@ -38,6 +40,7 @@ pub struct Entity {
     pub health: usize,
     pub name: String,
     pub inventory: Inventory,
+    pub loot: Option<String>,
     pub attacks: Vec<EntityAttack>
 }
 
@@ -51,6 +54,23 @@ impl Entity {
         
         &self.attacks[dist.sample(&mut rand::thread_rng())]
     }
+
+    /// Everything this entity drops when defeated: its fixed inventory, plus a roll on its loot table.
+    pub fn drops(&self, game: &GameData) -> Inventory {
+        let mut drops = self.inventory.clone();
+
+        if let Some(t) = &self.loot {
+            let rolled = game.loot_tables.get(t).unwrap().roll(game);
+
+            drops.currency.value += rolled.currency.value;
+
+            for (i, n) in &rolled.items {
+                drops.add(i, *n as isize);
+            }
+        }
+
+        drops
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -68,16 +88,117 @@ impl EntityInstance {
     }
 }
 
+/// How a character's interaction behaves once the player has already talked to them.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Repeat {
+    /// The interaction only ever happens once.
+    Once,
+    /// The interaction can't happen again until this many minutes have passed.
+    Cooldown(usize),
+    /// Every conversation after the first uses this interaction instead.
+    After(Box<InteractionType>)
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Character {
+    /// What the character's state is saved under. Defaults to their name.
+    pub id: Option<String>,
     pub name: String,
     pub description: String,
-    pub interaction: InteractionType
+    pub interaction: InteractionType,
+    pub repeat: Option<Repeat>,
+    pub schedule: Option<Vec<ScheduleEntry>>
+}
+
+impl Character {
+    pub fn id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// Checks that everything the character's interactions name exists.
+    pub fn validate(&self, game: &GameData) -> Result<(), String> {
+        let owner = format!("Character '{}'", self.id());
+        self.interaction.validate(&owner, game)?;
+
+        if let Some(Repeat::After(i)) = &self.repeat {
+            i.validate(&owner, game)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn talk(&self, game: &mut GameData, input: &mut InputController) -> Option<String> {
+        use Repeat::*;
+
+        let id = self.id();
+        let state = game.global.characters.get(&id).cloned().unwrap_or_default();
+
+        let interaction = match (&self.repeat, state.talked) {
+            (_, 0) | (None, _) => &self.interaction,
+            (Some(Once), _) => return Some(format!("{} has nothing more to say.", self.name)),
+            (Some(Cooldown(n)), _) => {
+                let ready = match &state.last {
+                    Some(t) => game.global.time.total() >= t.total() + n,
+                    None => true
+                };
+
+                if !ready {
+                    return Some(format!("{} has nothing new to say right now.", self.name));
+                }
+
+                &self.interaction
+            }
+            (Some(After(i)), _) => i
+        };
+
+        // This counts as talking before the interaction runs, so its conditions can tell which time it is.
+        let time = game.global.time.clone();
+        game.global.characters.entry(id.clone()).or_default().talk(time);
+
+        interaction.interact(Some(&id), game, input)
+    }
+}
+
+/// What the player has done with a character so far.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct CharacterState {
+    pub talked: usize,
+    pub last: Option<GameTime>,
+    /// Dialogue nodes the player has seen with this character, in the order first seen.
+    pub history: Vec<String>
+}
+
+impl CharacterState {
+    pub fn talk(&mut self, time: GameTime) {
+        self.talked += 1;
+        self.last = Some(time);
+    }
+
+    pub fn visit(&mut self, nodes: Vec<String>) {
+        for n in nodes {
+            if !self.history.contains(&n) {
+                self.history.push(n);
+            }
+        }
+    }
+}
+
+/// What started a fight, so winning it can mean something.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum CombatOrigin {
+    Camp(String),
+    /// An ambush on the way to this location.
+    Road(String),
+    /// A room in the dungeon the player is in.
+    Room(String)
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PlayerCombatData {
-    pub entities: Vec<EntityInstance>
+    pub entities: Vec<EntityInstance>,
+    pub origin: Option<CombatOrigin>
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -85,19 +206,68 @@ pub struct PlayerCombatData {
 pub enum PlayerStatus {
     Combat(PlayerCombatData),
     House(String),
+    /// Exploring the current location's dungeon, in this room.
+    Dungeon(String),
     Location,
     #[serde(alias = "none")]
     Idle
 }
 
+impl PlayerStatus {
+    pub fn name(&self) -> &'static str {
+        use PlayerStatus::*;
+
+        match self {
+            Combat(_) => "combat",
+            House(_) => "house",
+            Dungeon(_) => "dungeon",
+            Location => "location",
+            Idle => "idle"
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PlayerQuestData {
     pub assigned: Option<Vec<String>>,
-    pub completed: Option<Vec<String>>
+    pub completed: Option<Vec<String>>,
+    pub failed: Option<Vec<String>>,
+    #[serde(default)]
+    pub progress: HashMap<String, QuestProgress>,
+    /// When each assigned quest was taken on.
+    #[serde(default)]
+    pub started: HashMap<String, GameTime>
 }
 
 impl PlayerQuestData {
+    pub fn is_assigned(&self, q: &String) -> bool {
+        self.assigned.as_ref().is_some_and(|v| v.contains(q))
+    }
+
+    pub fn is_completed(&self, q: &String) -> bool {
+        self.completed.as_ref().is_some_and(|v| v.contains(q))
+    }
+
+    pub fn is_failed(&self, q: &String) -> bool {
+        self.failed.as_ref().is_some_and(|v| v.contains(q))
+    }
+
+    pub fn assign(&mut self, q: &String, time: &GameTime) {
+        if self.is_assigned(q) {
+            return;
+        }
+
+        self.started.insert(q.clone(), time.clone());
+
+        match &mut self.assigned {
+            Some(v) => v.push(q.clone()),
+            None => self.assigned = Some(vec![q.clone()])
+        }
+    }
+
     pub fn remove(&mut self, q: &String) {
+        self.started.remove(q);
+
         match &mut self.assigned {
             Some(v) => {
                 if v.contains(q) {
@@ -120,6 +290,15 @@ impl PlayerQuestData {
             None => self.completed = Some(vec![q.clone()])
         }
     }
+
+    pub fn fail(&mut self, q: &String) {
+        self.remove(q);
+
+        match &mut self.failed {
+            Some(v) => v.push(q.clone()),
+            None => self.failed = Some(vec![q.clone()])
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -127,20 +306,10 @@ pub struct PlayerStats {
     pub reputation: HashMap<String, usize>,
     pub defeated: HashMap<String, usize>,
     pub marks: Option<Vec<String>>,
-    pub log: Option<Vec<String>>
-}
-
-impl PlayerStats {
-    pub fn log(&self, last: bool) -> String {
-        match &self.log {
-            Some(v) => if last {
-                v.last().unwrap().clone()
-            } else { 
-                v.join("\n\n")
-            },
-            None => String::from("Nothing here...")
-        }
-    }
+    #[serde(default, deserialize_with = "journal::entries")]
+    pub log: Option<Vec<JournalEntry>>,
+    #[serde(default)]
+    pub titles: Option<Vec<String>>
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -149,6 +318,18 @@ pub struct PlayerVitality {
     pub effects: HashMap<String, usize>
 }
 
+impl PlayerVitality {
+    /// Applies a status effect for some cycles, extending it if it's already active.
+    /// Effects that would last no cycles are ignored.
+    pub fn add_effect(&mut self, e: &str, n: usize) {
+        if n == 0 {
+            return;
+        }
+
+        *self.effects.entry(e.to_string()).or_insert(0) += n;
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Player {
     pub name: String,
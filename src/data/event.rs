@@ -0,0 +1,60 @@
+extern crate serde;
+
+use serde::{Deserialize, Serialize};
+
+use super::common::{Condition, InteractionLine, Reward};
+use super::time::GameTime;
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// Once, at this time.
+    At(GameTime),
+    /// Over and over, this many minutes apart, starting at `from`.
+    /// Without one, the first time is one interval after the beginning of time.
+    Every {
+        minutes: usize,
+        from: Option<GameTime>
+    },
+    /// Once, as soon as the event's conditions are met.
+    When
+}
+
+impl Trigger {
+    /// How many times the event should have happened by now.
+    pub fn due(&self, time: &GameTime) -> usize {
+        use Trigger::*;
+
+        match self {
+            At(t) => (time.total() >= t.total()) as usize,
+            Every { minutes, from } => {
+                let minutes = (*minutes).max(1);
+
+                match from {
+                    Some(t) => match time.total().checked_sub(t.total()) {
+                        Some(n) => n / minutes + 1,
+                        None => 0
+                    },
+                    None => time.total() / minutes
+                }
+            }
+            When => 1
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Event {
+    pub trigger: Trigger,
+    /// Checked when the event is due. Timed events that don't meet them are skipped until next time.
+    pub conditions: Option<Vec<Condition>>,
+    pub lines: Option<Vec<InteractionLine>>,
+    pub rewards: Option<Vec<Reward>>
+}
+
+/// How often an event has happened so far.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct EventState {
+    pub fired: usize,
+    pub last: Option<GameTime>
+}
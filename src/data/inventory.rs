@@ -1,10 +1,12 @@
+extern crate rand;
 extern crate serde;
 
 use std::collections::HashMap;
 
-use serde::{Deserialize, Serialize};
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use super::common::Named;
+use super::common::{Named, Range};
 use super::data::GameData;
 use super::entity::Player;
 
@@ -61,7 +63,10 @@ pub struct Consumable {
 pub struct Equippable {
     pub strength: usize,
     #[serde(alias = "prof")]
-    pub proficiency: Vec<String>
+    pub proficiency: Vec<String>,
+    pub durability: Option<usize>,
+    /// Affixes that new instances of this item can roll with.
+    pub affixes: Option<Vec<AffixRoll>>
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -79,7 +84,8 @@ pub struct Item {
     pub name: String,
     pub description: String,
     #[serde(rename = "type")]
-    pub i_type: ItemType
+    pub i_type: ItemType,
+    pub weight: Option<f64>
 }
 
 impl Named for Item {
@@ -88,6 +94,147 @@ impl Named for Item {
     }
 }
 
+impl Item {
+    pub fn weight(&self) -> f64 {
+        self.weight.unwrap_or(0.0)
+    }
+
+    pub fn equippable(&self) -> Option<&Equippable> {
+        use ItemType::*;
+
+        match &self.i_type {
+            Weapon(e) | Armor(e) => Some(e),
+            _ => None
+        }
+    }
+
+    /// Whether this item is tracked as individual instances rather than a stacked count.
+    pub fn instanced(&self) -> bool {
+        self.equippable().is_some()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Affix {
+    pub name: String,
+    pub strength: isize
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AffixRoll {
+    pub name: String,
+    pub strength: isize,
+    /// The percent chance of a new instance having this affix.
+    pub chance: u8
+}
+
+/// A single weapon or armor. Content can write one out in full to give the player
+/// a specific named or enchanted item; any missing durability is filled in from its base.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ItemInstance {
+    pub base: String,
+    pub name: Option<String>,
+    pub durability: Option<Range>,
+    pub affixes: Option<Vec<Affix>>
+}
+
+impl ItemInstance {
+    /// A new instance of an item, rolling whatever affixes it can have.
+    pub fn from(item: &Item, base: String) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let affixes = item.equippable()
+            .and_then(|e| e.affixes.as_ref())
+            .map(|v| v.iter()
+                .filter(|a| rng.gen_range(0..100) < a.chance)
+                .map(|a| Affix {
+                    name: a.name.clone(),
+                    strength: a.strength
+                })
+                .collect::<Vec<Affix>>())
+            .filter(|v| !v.is_empty());
+
+        let mut instance = ItemInstance {
+            base,
+            name: None,
+            durability: None,
+            affixes
+        };
+
+        instance.fill(item);
+        instance
+    }
+
+    /// Gives the instance its base item's durability if it doesn't have any yet.
+    pub fn fill(&mut self, item: &Item) {
+        if self.durability.is_none() {
+            self.durability = item.equippable()
+                .and_then(|e| e.durability)
+                .map(Range::new);
+        }
+    }
+
+    pub fn broken(&self) -> bool {
+        matches!(&self.durability, Some(r) if r.value == 0)
+    }
+
+    pub fn damaged(&self) -> bool {
+        matches!(&self.durability, Some(r) if r.value < r.max)
+    }
+
+    /// Wears the item down by one point. Returns true if this broke it.
+    pub fn wear(&mut self) -> bool {
+        match &mut self.durability {
+            Some(r) if r.value > 0 => {
+                *r -= 1;
+                r.value == 0
+            }
+            _ => false
+        }
+    }
+
+    /// The item's strength including its affixes, or none if it's broken.
+    pub fn strength(&self, game: &GameData) -> usize {
+        if self.broken() {
+            return 0;
+        }
+
+        let base = game.items.get(&self.base).unwrap()
+            .equippable()
+            .map_or(0, |e| e.strength) as isize;
+
+        let affixes = self.affixes.iter()
+            .flatten()
+            .map(|a| a.strength)
+            .sum::<isize>();
+
+        (base + affixes).max(0) as usize
+    }
+
+    pub fn display(&self, game: &GameData) -> String {
+        let name = match &self.name {
+            Some(s) => s.clone(),
+            None => game.items.get(&self.base).unwrap().name.clone()
+        };
+
+        let affixes = match &self.affixes {
+            Some(v) if !v.is_empty() => format!(" [{}]", v.iter()
+                .map(|a| a.name.clone())
+                .collect::<Vec<String>>()
+                .join(", ")),
+            _ => String::new()
+        };
+
+        let durability = match &self.durability {
+            Some(r) if r.value == 0 => String::from(" (broken)"),
+            Some(r) => format!(" ({}/{})", r.value, r.max),
+            None => String::new()
+        };
+
+        format!("{}{}{}", name, affixes, durability)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Currency {
     pub value: f64
@@ -111,24 +258,90 @@ impl Currency {
     pub fn display(n: f64, game: &GameData) -> String {
         format!("{:.2}{}", n, game.config.world.currency.symbol)
     }
+
+    /// Displays an amount using the currency's name, like "1 crown" or "3 crowns".
+    pub fn words(n: f64, game: &GameData) -> String {
+        let currency = &game.config.world.currency;
+        let name = if n == 1.0 { &currency.singular } else { &currency.plural };
+
+        if n.fract() == 0.0 {
+            format!("{} {}", n, name)
+        } else {
+            format!("{:.2} {}", n, name)
+        }
+    }
+}
+
+/// An equipment slot as it's saved. Older saves only have the item's ID.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Slot {
+    Id(String),
+    Instance(ItemInstance)
+}
+
+fn slot<'de, D: Deserializer<'de>>(d: D) -> Result<Option<ItemInstance>, D::Error> {
+    Ok(Option::<Slot>::deserialize(d)?.map(|s| match s {
+        Slot::Id(base) => ItemInstance {
+            base,
+            name: None,
+            durability: None,
+            affixes: None
+        },
+        Slot::Instance(i) => i
+    }))
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Equipped {
-    pub weapon: Option<String>,
-    pub armor: Option<String>,
+    #[serde(default, deserialize_with = "slot")]
+    pub weapon: Option<ItemInstance>,
+    #[serde(default, deserialize_with = "slot")]
+    pub armor: Option<ItemInstance>,
+}
+
+impl Equipped {
+    pub fn all(&self) -> Vec<&ItemInstance> {
+        self.weapon.iter()
+            .chain(self.armor.iter())
+            .collect::<Vec<&ItemInstance>>()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Inventory {
     pub items: HashMap<String, usize>,
+    #[serde(default)]
+    pub instances: Vec<ItemInstance>,
     pub currency: Currency,
     pub equipped: Equipped
 }
 
 impl Inventory {
+    pub fn new() -> Self {
+        Inventory {
+            items: HashMap::new(),
+            instances: Vec::new(),
+            currency: Currency { value: 0.0 },
+            equipped: Equipped {
+                weapon: None,
+                armor: None
+            }
+        }
+    }
+
+    /// Adds or removes stacked items without any capacity checks.
+    /// Removing also takes unequipped instances with a matching base.
+    /// Use `GameData::give` for anything the player picks up.
     pub fn add(&mut self, i: &String, n: isize) {
-        if self.items.contains_key(i) {
+        if n < 0 && !self.items.contains_key(i) {
+            for _ in 0..n.unsigned_abs() {
+                match self.instances.iter().rposition(|x| &x.base == i) {
+                    Some(p) => { self.instances.remove(p); }
+                    None => break
+                }
+            }
+        } else if self.items.contains_key(i) {
             let item = self.items.get_mut(i).unwrap();
         
             if n > 0 {
@@ -147,12 +360,60 @@ impl Inventory {
         }
     }
 
+    /// Fills in the durability of any instances that were saved without it.
+    pub fn fill(&mut self, items: &HashMap<String, Item>) {
+        let instances = self.instances.iter_mut()
+            .chain(self.equipped.weapon.iter_mut())
+            .chain(self.equipped.armor.iter_mut());
+
+        for i in instances {
+            if let Some(item) = items.get(&i.base) {
+                i.fill(item);
+            }
+        }
+    }
+
+    /// Removes items by ID wherever the player has them, taking from the bag before their equipment.
+    pub fn take(&mut self, i: &String, n: usize) {
+        let stacked = self.items.get(i).copied().unwrap_or(0).min(n);
+        self.add(i, -(stacked as isize));
+
+        for _ in stacked..n {
+            if let Some(p) = self.instances.iter().rposition(|x| &x.base == i) {
+                self.instances.remove(p);
+            } else if self.equipped.weapon.as_ref().is_some_and(|x| &x.base == i) {
+                self.equipped.weapon = None;
+            } else if self.equipped.armor.as_ref().is_some_and(|x| &x.base == i) {
+                self.equipped.armor = None;
+            } else {
+                break;
+            }
+        }
+    }
+
     pub fn get(&self, item: &String) -> Option<&usize> {
         self.items.get(item)
     }
 
+    /// Every item the player is carrying and how many, including instances and equipment.
+    pub fn counts(&self) -> HashMap<String, usize> {
+        let mut counts = self.items.clone();
+
+        for i in self.instances.iter().chain(self.equipped.all()) {
+            *counts.entry(i.base.clone()).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    pub fn weight(&self, game: &GameData) -> f64 {
+        self.counts().iter()
+            .map(|(i, v)| game.items.get(i).unwrap().weight() * *v as f64)
+            .sum()
+    }
+
     pub fn display(&self, game: &GameData) -> Option<Vec<String>> {
-        if self.items.is_empty() {
+        if self.items.is_empty() && self.instances.is_empty() {
             return None;
         }
 
@@ -161,10 +422,20 @@ impl Inventory {
                 .map(|(i, v)| {
                     format!("{} {}", v, game.items.get(i).unwrap().name)
                 })
+                .chain(self.instances.iter().map(|i| i.display(game)))
                 .collect::<Vec<String>>()
         )
     }
 
+    pub fn display_equipped(&self, game: &GameData) -> String {
+        let slot = |i: &Option<ItemInstance>| match i {
+            Some(i) => i.display(game),
+            None => String::from("Nothing")
+        };
+
+        format!("Weapon: {}\nArmor: {}", slot(&self.equipped.weapon), slot(&self.equipped.armor))
+    }
+
     pub fn display_line(&self, game: &GameData) -> String {
         match self.display(game) {
             Some(v) => v.join(", "),
@@ -183,12 +454,4 @@ impl Inventory {
             None => String::from("Nothing")
         }
     }
-
-    pub fn drop(&self, other: &mut Inventory) {
-        other.currency.value += self.currency.value;
-
-        for (i, v) in &self.items {
-            *other.items.get_mut(i).unwrap() += v;
-        }
-    }
 }
\ No newline at end of file
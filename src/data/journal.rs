@@ -0,0 +1,216 @@
+extern crate serde;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::data::GameData;
+use super::time::GameTime;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalCategory {
+    Quest,
+    Lore,
+    People,
+    Places
+}
+
+impl JournalCategory {
+    pub fn name(&self) -> &'static str {
+        use JournalCategory::*;
+
+        match self {
+            Quest => "quest",
+            Lore => "lore",
+            People => "people",
+            Places => "places"
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        use JournalCategory::*;
+
+        match s.to_lowercase().as_str() {
+            "quest" | "quests" => Some(Quest),
+            "lore" => Some(Lore),
+            "people" => Some(People),
+            "places" => Some(Places),
+            _ => None
+        }
+    }
+}
+
+/// Journal text as authors write it, before it's stamped with a time and place.
+/// Plain strings are filed under lore.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum LogText {
+    Plain(String),
+    Detailed {
+        text: String,
+        category: Option<JournalCategory>,
+        quest: Option<String>
+    }
+}
+
+impl LogText {
+    pub fn entry(&self, game: &GameData) -> JournalEntry {
+        let (text, category, quest) = match self {
+            LogText::Plain(s) => (s.clone(), JournalCategory::Lore, None),
+            LogText::Detailed { text, category, quest } => (
+                text.clone(),
+                category.clone().unwrap_or(JournalCategory::Lore),
+                quest.clone()
+            )
+        };
+
+        JournalEntry {
+            text,
+            time: game.global.time.clone(),
+            location: game.global.player.location.clone(),
+            category,
+            quest
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JournalEntry {
+    pub text: String,
+    pub time: GameTime,
+    pub location: String,
+    pub category: JournalCategory,
+    pub quest: Option<String>
+}
+
+/// A journal entry as it's saved. Older saves only have the text.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedEntry {
+    Plain(String),
+    Entry(JournalEntry)
+}
+
+/// Loads the player's journal, filing plain text from older saves under lore with no time or place.
+pub fn entries<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<JournalEntry>>, D::Error> {
+    let saved = Option::<Vec<SavedEntry>>::deserialize(d)?;
+
+    Ok(saved.map(|v| v.into_iter()
+        .map(|e| match e {
+            SavedEntry::Plain(text) => JournalEntry {
+                text,
+                time: GameTime { day: 0, min: 0 },
+                location: String::new(),
+                category: JournalCategory::Lore,
+                quest: None
+            },
+            SavedEntry::Entry(e) => e
+        })
+        .collect::<Vec<JournalEntry>>()))
+}
+
+impl JournalEntry {
+    pub const PAGE_SIZE: usize = 5;
+
+    pub fn display(&self, game: &GameData) -> String {
+        let location = match game.locations.get(&self.location) {
+            Some(l) => l.name.clone(),
+            None => self.location.clone()
+        };
+
+        let quest = match self.quest.as_ref().and_then(|q| game.quests.get(q)) {
+            Some(q) => format!(", {}", q.name),
+            None => String::new()
+        };
+
+        let day = match self.time.date(game) {
+            Some(d) => d.to_string(),
+            None => format!("Day {}", self.time.day)
+        };
+
+        // Entries from older saves weren't written down with a time or place.
+        let when = if self.location.is_empty() {
+            String::from("Earlier")
+        } else {
+            format!("{}, {} in {}", day, self.time.display_time(), location)
+        };
+
+        format!("{} ({}{})\n{}",
+            when,
+            self.category.name(),
+            quest,
+            self.text
+        )
+    }
+
+    /// Displays one page of entries, newest last.
+    pub fn display_page(v: &[&JournalEntry], page: usize, game: &GameData) -> String {
+        if v.is_empty() {
+            return String::from("Nothing here...");
+        }
+
+        let pages = v.len().div_ceil(Self::PAGE_SIZE);
+        let page = page.clamp(1, pages);
+        let start = (page - 1) * Self::PAGE_SIZE;
+
+        format!("{}\n\nPage {} of {}",
+            v[start..(start + Self::PAGE_SIZE).min(v.len())].iter()
+                .map(|e| e.display(game))
+                .collect::<Vec<String>>()
+                .join("\n\n"),
+            page,
+            pages
+        )
+    }
+
+    /// Handles the `log` command's arguments: an optional category or `search <text>`, then an optional page.
+    pub fn query(args: &[String], game: &GameData) -> String {
+        let entries = game.global.player.stats.log.iter()
+            .flatten()
+            .collect::<Vec<&JournalEntry>>();
+
+        if let Some("search") = args.first().map(|s| s.as_str()) {
+            let text = args[1..].join(" ").to_lowercase();
+
+            if text.is_empty() {
+                return String::from("You need to provide something to search for.");
+            }
+
+            let found = entries.into_iter()
+                .filter(|e| e.text.to_lowercase().contains(&text))
+                .collect::<Vec<&JournalEntry>>();
+
+            return Self::display_page(&found, 1, game);
+        }
+
+        let mut rest = args;
+
+        let category = match rest.first().and_then(|s| JournalCategory::parse(s)) {
+            Some(c) => {
+                rest = &rest[1..];
+                Some(c)
+            }
+            None => None
+        };
+
+        let page = match rest.first() {
+            Some(s) => match s.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => return String::from("That's not a valid journal category or page.")
+            },
+            None => 1
+        };
+
+        let filtered = entries.into_iter()
+            .filter(|e| category.as_ref().is_none_or(|c| &e.category == c))
+            .collect::<Vec<&JournalEntry>>();
+
+        Self::display_page(&filtered, page, game)
+    }
+
+    pub fn recent(game: &GameData) -> String {
+        match game.global.player.stats.log.as_ref().and_then(|v| v.last()) {
+            Some(e) => e.display(game),
+            None => String::from("Nothing here...")
+        }
+    }
+}
@@ -2,15 +2,18 @@ extern crate rand;
 extern crate serde;
 
 use rand::seq::SliceRandom;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
 
-use super::common::{InteractionLine, Named};
+use super::common::{CombatInteraction, Condition, InteractionLine, Named, Reward, StaticInteraction};
 use super::data::GameData;
 use super::entity::Character;
+use super::loot::Bounds;
 use super::inventory::Currency;
-use super::time::GameTime;
+use super::road::{Road, Route};
+use super::template;
+use super::time::{GameTime, Hours};
 use super::super::input::controller::InputController;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -28,15 +31,74 @@ impl Coordinates {
         let f1 = self.as_float();
         let f2 =    c.as_float();
 
-        ((f2.1 - f1.1) + (f2.0 - f1.0)).abs().sqrt()
+        (f2.0 - f1.0).hypot(f2.1 - f1.1)
+    }
+}
+
+/// Where someone is during part of their schedule.
+pub enum Whereabouts {
+    House(String),
+    /// Out and about somewhere, but not in any house.
+    Location(String),
+    Away
+}
+
+/// A span of time someone spends somewhere. An entry with neither a house nor a location
+/// means they're away from anywhere the player can find them.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScheduleEntry {
+    pub hours: Option<Hours>,
+    pub days: Option<Bounds<usize>>,
+    /// Needs a calendar to mean anything.
+    pub weekdays: Option<Vec<String>>,
+    pub house: Option<String>,
+    pub location: Option<String>
+}
+
+impl ScheduleEntry {
+    pub fn active(&self, game: &GameData) -> bool {
+        let time = &game.global.time;
+
+        let hours = match &self.hours {
+            Some(h) => h.contains(time),
+            None => true
+        };
+
+        let days = match &self.days {
+            Some(b) => time.day >= b.min && time.day <= b.max,
+            None => true
+        };
+
+        let weekdays = match &self.weekdays {
+            Some(v) => time.date(game)
+                .and_then(|d| d.weekday)
+                .is_some_and(|w| v.iter().any(|x| x.eq_ignore_ascii_case(w))),
+            None => true
+        };
+
+        hours && days && weekdays
+    }
+
+    /// Where someone following a schedule is right now. Anyone whose schedule doesn't say is at home.
+    pub fn find(schedule: &Option<Vec<ScheduleEntry>>, home: &str, game: &GameData) -> Whereabouts {
+        match schedule.iter().flatten().find(|e| e.active(game)) {
+            Some(ScheduleEntry { house: Some(h), .. }) => Whereabouts::House(h.clone()),
+            Some(ScheduleEntry { location: Some(l), .. }) => Whereabouts::Location(l.clone()),
+            Some(_) => Whereabouts::Away,
+            None => Whereabouts::House(home.to_string())
+        }
     }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Citizen {
     pub name: String,
-    #[serde(alias = "lines")]
-    pub dialogue: Vec<String>
+    #[serde(alias = "lines", deserialize_with = "template::checked_all")]
+    pub dialogue: Vec<String>,
+    /// What the citizen says at night instead.
+    #[serde(default, deserialize_with = "template::checked_option")]
+    pub night: Option<Vec<String>>,
+    pub schedule: Option<Vec<ScheduleEntry>>
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -46,11 +108,63 @@ pub enum HouseResident {
     Character(Character)
 }
 
+impl HouseResident {
+    pub fn name(&self) -> &String {
+        use HouseResident::*;
+
+        match self {
+            Citizen(c) => &c.name,
+            Character(c) => &c.name
+        }
+    }
+
+    /// Where this resident of `home` is right now.
+    pub fn whereabouts(&self, home: &str, game: &GameData) -> Whereabouts {
+        use HouseResident::*;
+
+        match self {
+            Citizen(c) => ScheduleEntry::find(&c.schedule, home, game),
+            Character(c) => ScheduleEntry::find(&c.schedule, home, game)
+        }
+    }
+
+    pub fn talk(&self, game: &mut GameData, input: &mut InputController) -> Option<String> {
+        use HouseResident::*;
+
+        match self {
+            Citizen(c) => {
+                let lines = match &c.night {
+                    Some(v) if game.global.time.is_night() => v,
+                    _ => &c.dialogue
+                };
+
+                lines.choose(&mut rand::thread_rng())
+                    .map(|s| template::render(s, game))
+            }
+            Character(c) => {
+                game.turn_in(Some(&c.id()));
+                c.talk(game, input)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HouseNight {
+    /// Shown instead of the house's usual entry at night.
+    pub entry: Option<Vec<InteractionLine>>,
+    #[serde(default)]
+    pub locked: bool,
+    /// Lets the player in even when the house is locked.
+    pub unlock: Option<Vec<Condition>>
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct House {
     pub name: String,
     pub entry: Vec<InteractionLine>,
-    pub residents: Vec<HouseResident>
+    pub residents: Vec<HouseResident>,
+    pub night: Option<HouseNight>
 }
 
 impl Named for House {
@@ -60,26 +174,21 @@ impl Named for House {
 }
 
 impl House {
-    pub fn list(&self) -> Vec<String> {
-        use HouseResident::*;
-
-        self.residents.iter()
-            .map(|r| match r {
-                Citizen(c) => c.name.clone(),
-                Character(c) => c.name.clone()
-            })
-            .collect::<Vec<String>>()
+    /// Whether the player can get in right now.
+    pub fn open(&self, game: &GameData) -> bool {
+        match &self.night {
+            Some(n) if n.locked && game.global.time.is_night() => match &n.unlock {
+                Some(v) => Condition::check_all(v, game),
+                None => false
+            },
+            _ => true
+        }
     }
 
-    pub fn talk(&self, resident: usize, game: &mut GameData, input: &mut InputController) -> Option<String> {
-        use HouseResident::*;
-
-        match &self.residents[resident] {
-            Citizen(c) => match c.dialogue.choose(&mut rand::thread_rng()) {
-                Some(s) => Some(s.clone()),
-                None => None
-            },
-            Character(c) => c.interaction.interact(game, input)
+    pub fn entry(&self, game: &GameData) -> &Vec<InteractionLine> {
+        match &self.night {
+            Some(HouseNight { entry: Some(v), .. }) if game.global.time.is_night() => v,
+            _ => &self.entry
         }
     }
 }
@@ -91,28 +200,64 @@ pub struct Inn {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Tavern {
-    name: String,
-    shop: Vec<String>,
-    quests: Vec<String>
+    pub name: String,
+    pub shop: Vec<String>,
+    pub quests: Vec<String>,
+    /// When the tavern is open. Always open if unset.
+    pub hours: Option<Hours>
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Shop {
-    name: String,
-    items: Vec<String>
+    pub name: String,
+    pub items: Vec<String>,
+    /// The cost of restoring one point of durability, if this shop does repairs.
+    pub repair: Option<f64>,
+    /// When the shop is open. Always open if unset.
+    pub hours: Option<Hours>
+}
+
+/// Whether a place with these opening hours is open, or a message saying it's closed.
+pub fn opening(name: &str, hours: &Option<Hours>, game: &GameData) -> Result<(), String> {
+    match hours {
+        Some(h) if !h.contains(&game.global.time) => Err(format!("The {} is closed. It's open from {}.", name, h)),
+        _ => Ok(())
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Palace {
-    name: String,
-    ruler: Character
+    pub name: String,
+    pub ruler: Box<Character>,
+    /// The reputation the player needs here to be granted an audience.
+    pub reputation: Option<usize>,
+    pub conditions: Option<Vec<Condition>>,
+    /// Shown when the player is turned away.
+    pub refused: Option<Vec<InteractionLine>>
+}
+
+impl Palace {
+    pub fn admits(&self, location: &String, game: &GameData) -> bool {
+        let reputation = match self.reputation {
+            Some(n) => game.locations.get(location).unwrap()
+                .reputation(location, game).0 >= n,
+            None => true
+        };
+
+        let conditions = match &self.conditions {
+            Some(v) => Condition::check_all(v, game),
+            None => true
+        };
+
+        reputation && conditions
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct TownData {
-    houses: Vec<String>,
-    inn: Inn,
-    tavern: Tavern
+    pub houses: Vec<String>,
+    pub inn: Inn,
+    pub tavern: Tavern
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -132,16 +277,170 @@ pub struct CapitalData {
     pub palace: Palace
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct BanditData {
+    pub encounter: CombatInteraction,
+    /// A loot table the player gets once the camp is cleared.
+    pub loot: Option<String>,
+    /// Shown when visiting a camp that's already been cleared.
+    pub cleared: Option<Vec<InteractionLine>>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MonumentData {
+    pub lore: Vec<InteractionLine>,
+    /// Given the first time the player visits.
+    pub blessing: Option<StaticInteraction>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Exit {
+    pub to: String,
+    pub conditions: Option<Vec<Condition>>,
+    /// Shown when the player doesn't meet the conditions to go through.
+    pub locked: Option<Vec<InteractionLine>>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Room {
+    pub name: String,
+    pub description: Vec<InteractionLine>,
+    /// Where the player can go from here, by direction.
+    pub exits: HashMap<String, Exit>,
+    /// A fight the player walks into until they win it.
+    pub encounter: Option<CombatInteraction>,
+    /// What the player finds when they search the room, once.
+    pub treasure: Option<Vec<Reward>>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DungeonData {
+    pub entry: Vec<InteractionLine>,
+    pub conditions: Option<Vec<Condition>>,
+    /// Shown when the player doesn't meet the conditions to get in.
+    pub locked: Option<Vec<InteractionLine>>,
+    pub start: String,
+    pub rooms: HashMap<String, Room>
+}
+
+impl DungeonData {
+    /// Checks that the start and every exit lead to rooms that exist.
+    pub fn validate(&self, id: &str) -> Result<(), String> {
+        let exits = self.rooms.values()
+            .flat_map(|r| r.exits.values().map(|e| &e.to));
+
+        for r in std::iter::once(&self.start).chain(exits) {
+            if !self.rooms.contains_key(r) {
+                return Err(format!("Dungeon '{}' leads to a room '{}' that doesn't exist", id, r));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RoomState {
+    #[serde(default)]
+    pub cleared: bool,
+    #[serde(default)]
+    pub looted: bool
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Ware {
+    pub item: String,
+    pub price: f64,
+    /// How many the trader can sell each day. Unlimited if unset.
+    pub stock: Option<usize>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TraderData {
+    pub name: String,
+    pub greeting: Vec<InteractionLine>,
+    pub wares: Vec<Ware>
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum LocationType {
     Town(TownData),
     City(CityData),
     Capital(CapitalData),
-    Bandit,
-    Monument,
-    Dungeon,
-    Trader
+    Bandit(BanditData),
+    Monument(MonumentData),
+    Dungeon(DungeonData),
+    Trader(TraderData)
+}
+
+impl LocationType {
+    pub fn name(&self) -> &'static str {
+        use LocationType::*;
+
+        match self {
+            Town(_) => "town",
+            City(_) => "city",
+            Capital(_) => "capital",
+            Bandit(_) => "bandit camp",
+            Monument(_) => "monument",
+            Dungeon(_) => "dungeon",
+            Trader(_) => "trader"
+        }
+    }
+
+    /// The character this kind of location is drawn with on the map.
+    pub fn glyph(&self) -> char {
+        use LocationType::*;
+
+        match self {
+            Town(_) => 'o',
+            City(_) => 'O',
+            Capital(_) => '#',
+            Bandit(_) => 'x',
+            Monument(_) => '^',
+            Dungeon(_) => 'D',
+            Trader(_) => '$'
+        }
+    }
+}
+
+/// What the player has done at a location that isn't a town.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PlaceState {
+    #[serde(default)]
+    pub cleared: bool,
+    #[serde(default)]
+    pub blessed: bool,
+    /// How many of each ware the player has bought from a trader on `day`.
+    #[serde(default)]
+    pub sold: HashMap<String, usize>,
+    #[serde(default)]
+    pub day: usize,
+    /// Dungeon rooms, by ID.
+    #[serde(default)]
+    pub rooms: HashMap<String, RoomState>
+}
+
+impl PlaceState {
+    /// How many of a ware the player has bought today.
+    pub fn bought(&self, item: &String, day: usize) -> usize {
+        if self.day != day {
+            return 0;
+        }
+
+        self.sold.get(item).copied().unwrap_or(0)
+    }
+
+    pub fn buy(&mut self, item: &str, day: usize) {
+        // A new day means the trader has restocked.
+        if self.day != day {
+            self.sold.clear();
+            self.day = day;
+        }
+
+        *self.sold.entry(item.to_string()).or_insert(0) += 1;
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -154,7 +453,8 @@ pub struct Location {
     pub entry: Option<usize>,
     #[serde(rename = "type")]
     pub l_type: LocationType,
-    pub quests: Option<Vec<String>>
+    pub quests: Option<Vec<String>>,
+    pub roads: Option<Vec<Road>>
 }
 
 impl Named for Location {
@@ -163,58 +463,23 @@ impl Named for Location {
     }
 }
 
-pub enum ReputationLevel {
-    Lowest,
-    Low,
-    Medium,
-    High,
-    Highest
-}
-
-impl ReputationLevel {
-    pub fn name(l: ReputationLevel) -> &'static str {
-        use ReputationLevel::*;
+impl Location {
+    /// The reputation the player starts with here.
+    pub fn baseline(&self, game: &GameData) -> usize {
+        let config = &game.config.reputation;
 
-        match l {
-            Lowest => "hostile",
-            Low => "suspicious",
-            Medium => "indifferent",
-            High => "esteemed",
-            Highest => "benevolent"
-        }
+        self.entry.unwrap_or(config.default).min(config.max)
     }
 
-    pub fn value(rep: usize) -> Self {
-        use ReputationLevel::*;
-
-        match rep {
-            0..=9 => Lowest,
-            10..=24 => Low,
-            25..=59 => Medium,
-            60..=84 => High,
-            85..=100 => Highest,
-            _ => Medium
+    pub fn reputation(&self, id: &String, game: &GameData) -> (usize, bool) {
+        match game.global.player.stats.reputation.get(id) {
+            Some(n) => (*n, false),
+            None => (self.baseline(game), true)
         }
     }
-}
 
-impl Location {
-    pub fn reputation(&self, id: &String, reputation: &HashMap<String, usize>) -> (usize, bool) {
-        if !reputation.contains_key(id) {
-            let value = match self.entry {
-                Some(n) => n,
-                None => 50
-            };
-
-            //reputation.insert(id.clone(), value);
-            (value, true)
-        } else {
-            (*reputation.get(id).unwrap(), false)
-        }
-    }
-
-    pub fn entry(&self, id: &String, reputation: &HashMap<String, usize>) -> (String, usize, bool) {
-        let rep = self.reputation(&id, reputation);
+    pub fn entry(&self, id: &String, game: &GameData) -> (String, usize, bool) {
+        let rep = self.reputation(id, game);
 
         let first = format!("You're entering {}{}", 
             self.name,
@@ -223,21 +488,33 @@ impl Location {
 
         let second = format!("Your reputation here is {} ({}).",
             rep.0,
-            ReputationLevel::name(ReputationLevel::value(rep.0))
+            game.config.reputation.tier(rep.0)
         );
 
         (format!("{} {}", first, second), rep.0, rep.1)
     }
 
-    pub fn travel_prompt(&self, next: &Location, game: &GameData) -> (String, f64, usize) {
-        let dist = self.position.dist(&next.position);
-        let cost = dist * game.config.world.currency.dist_cost as f64;
-        let time = (dist * 20.0) as usize;
+    pub fn travel_prompt(&self, next: &Location, route: &Route, game: &GameData) -> (String, f64, usize) {
+        let cost = route.distance() * game.config.world.currency.dist_cost as f64;
+        let mut time = match game.encumbrance() {
+            Some(n) => route.cost() * 20.0 * n,
+            None => route.cost() * 20.0
+        };
 
-        let prompt = format!("You are traveling from {} to {}.\n\nYou can ride the carriage, costing {}, or...\nYou can walk, taking {}.",
+        let night = game.global.time.is_night();
+
+        if night {
+            time *= game.config.world.night.as_ref().and_then(|n| n.slowdown).unwrap_or(1.0);
+        }
+
+        let time = time as usize;
+
+        let prompt = format!("You are traveling from {} to {}.\n\nRoute:\n{}\n\nYou can ride the carriage, costing {}, or...\nYou can walk{}, taking {}.",
             self.name,
             next.name,
+            route.display(game),
             Currency::display(cost, game),
+            if night { " in the dark" } else { "" },
             GameTime::duration(time)
         );
 
@@ -260,5 +537,48 @@ impl Location {
                 (h.clone(), house.name.clone())
             })
             .collect::<Vec<(String, String)>>())
-    }  
+    }
+
+    pub fn shops(&self) -> Option<&Vec<Shop>> {
+        use LocationType::*;
+
+        match &self.l_type {
+            City(c) => Some(&c.shops),
+            Capital(c) => Some(&c.shops),
+            _ => None
+        }
+    }
+
+    /// Whether a quest is given out here, either directly or on a tavern's board.
+    pub fn offers(&self, quest: &String) -> bool {
+        let own = self.quests.iter().flatten().any(|q| q == quest);
+        let tavern = self.tavern().is_some_and(|t| t.quests.contains(quest));
+
+        own || tavern
+    }
+
+    pub fn palace(&self) -> Option<&Palace> {
+        match &self.l_type {
+            LocationType::Capital(c) => Some(&c.palace),
+            _ => None
+        }
+    }
+
+    pub fn dungeon(&self) -> Option<&DungeonData> {
+        match &self.l_type {
+            LocationType::Dungeon(d) => Some(d),
+            _ => None
+        }
+    }
+
+    pub fn tavern(&self) -> Option<&Tavern> {
+        use LocationType::*;
+
+        match &self.l_type {
+            Town(t) => Some(&t.tavern),
+            City(c) => Some(&c.tavern),
+            Capital(c) => Some(&c.tavern),
+            _ => None
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,129 @@
+extern crate rand;
+extern crate serde;
+
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand::distributions::{WeightedIndex, Distribution};
+use serde::Deserialize;
+
+use super::data::GameData;
+use super::inventory::Inventory;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Bounds<T> {
+    pub min: T,
+    pub max: T
+}
+
+impl Bounds<usize> {
+    pub fn roll(&self) -> usize {
+        rand::thread_rng().gen_range(self.min..=self.max.max(self.min))
+    }
+}
+
+impl Bounds<f64> {
+    pub fn roll(&self) -> f64 {
+        if self.max <= self.min {
+            self.min
+        } else {
+            rand::thread_rng().gen_range(self.min..=self.max)
+        }
+    }
+}
+
+/// A single weighted outcome. An entry with neither an item nor a table drops nothing.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LootEntry {
+    pub weight: u8,
+    pub item: Option<String>,
+    pub count: Option<Bounds<usize>>,
+    pub table: Option<String>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LootTable {
+    pub guaranteed: Option<HashMap<String, Bounds<usize>>>,
+    pub currency: Option<Bounds<f64>>,
+    pub rolls: Option<usize>,
+    pub entries: Option<Vec<LootEntry>>
+}
+
+impl LootTable {
+    /// Nested tables deeper than this are ignored, so a table can't loop forever.
+    pub const MAX_DEPTH: usize = 8;
+
+    /// Checks that every item and nested table this table can drop exists.
+    pub fn validate(&self, id: &str, game: &GameData) -> Result<(), String> {
+        let entries = self.entries.iter().flatten();
+
+        let items = self.guaranteed.iter()
+            .flat_map(|m| m.keys())
+            .chain(entries.clone().filter_map(|e| e.item.as_ref()));
+
+        for i in items {
+            if !game.items.contains_key(i) {
+                return Err(format!("Loot table '{}' drops an item '{}' that doesn't exist", id, i));
+            }
+        }
+
+        for t in entries.filter_map(|e| e.table.as_ref()) {
+            if !game.loot_tables.contains_key(t) {
+                return Err(format!("Loot table '{}' rolls on a table '{}' that doesn't exist", id, t));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn roll(&self, game: &GameData) -> Inventory {
+        let mut result = Inventory::new();
+        self.roll_into(&mut result, game, 0);
+
+        result
+    }
+
+    fn roll_into(&self, result: &mut Inventory, game: &GameData, depth: usize) {
+        if depth > Self::MAX_DEPTH {
+            return;
+        }
+
+        if let Some(b) = &self.currency {
+            result.currency.value += b.roll();
+        }
+
+        if let Some(m) = &self.guaranteed {
+            for (i, b) in m {
+                result.add(i, b.roll() as isize);
+            }
+        }
+
+        let entries = match &self.entries {
+            Some(v) if !v.is_empty() => v,
+            _ => return
+        };
+
+        let weights = entries.iter()
+            .map(|e| e.weight)
+            .collect::<Vec<u8>>();
+
+        // A table whose weights are all zero has nothing to roll.
+        let dist = match WeightedIndex::new(&weights) {
+            Ok(d) => d,
+            Err(_) => return
+        };
+
+        for _ in 0..self.rolls.unwrap_or(1) {
+            let entry = &entries[dist.sample(&mut rand::thread_rng())];
+
+            if let Some(i) = &entry.item {
+                let n = entry.count.as_ref().map_or(1, |b| b.roll());
+                result.add(i, n as isize);
+            }
+
+            if let Some(t) = &entry.table {
+                game.loot_tables.get(t).unwrap().roll_into(result, game, depth + 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::data::GameData;
+use super::location::Location;
+
+/// Where the player is, drawn over whatever kind of location it is.
+pub const PLAYER_GLYPH: char = '@';
+
+/// How wide the map can be, given the terminal's width if it's known.
+fn columns(terminal: Option<usize>) -> usize {
+    terminal.unwrap_or(80).clamp(20, 200)
+}
+
+/// Locations the player has been to, which are the ones they have a reputation in.
+fn known(game: &GameData) -> Vec<(&String, &Location)> {
+    let player = &game.global.player;
+
+    let mut v = game.locations.iter()
+        .filter(|(k, _)| player.stats.reputation.contains_key(*k) || *k == &player.location)
+        .collect::<Vec<(&String, &Location)>>();
+
+    v.sort_by(|a, b| a.0.cmp(b.0));
+    v
+}
+
+/// Draws the known world to fit the terminal's width. Without a zoom, the map is fit around
+/// every known location; with one, it's scaled up by that much and centered on the player.
+pub fn render(game: &GameData, zoom: Option<f64>, terminal: Option<usize>) -> String {
+    let locations = known(game);
+    let current = game.location();
+
+    // Leave room for the border, and keep the map short enough to read without scrolling.
+    let width = columns(terminal) - 2;
+    let height = (width / 4).clamp(5, 20);
+
+    let xs = locations.iter().map(|(_, l)| l.position.x as f64);
+    let ys = locations.iter().map(|(_, l)| l.position.y as f64);
+
+    let (min_x, max_x) = xs.fold((f64::MAX, f64::MIN), |(a, b), x| (a.min(x), b.max(x)));
+    let (min_y, max_y) = ys.fold((f64::MAX, f64::MIN), |(a, b), y| (a.min(y), b.max(y)));
+
+    // Columns per unit of distance. Each row covers twice as much, since characters are tall.
+    let fit = ((width - 1) as f64 / (max_x - min_x).max(1.0))
+        .min(2.0 * (height - 1) as f64 / (max_y - min_y).max(1.0));
+
+    let (scale, center) = match zoom {
+        Some(z) => (fit * z, current.position.as_float()),
+        None => (fit, ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0))
+    };
+
+    let mut grid = vec![vec![' '; width]; height];
+
+    let mut place = |l: &Location, glyph: char| {
+        let (x, y) = l.position.as_float();
+        let col = (width / 2) as f64 + (x - center.0) * scale;
+        let row = (height / 2) as f64 + (y - center.1) * scale / 2.0;
+
+        if col >= 0.0 && row >= 0.0 && (col as usize) < width && (row as usize) < height {
+            grid[row as usize][col as usize] = glyph;
+        }
+    };
+
+    for (_, l) in &locations {
+        place(l, l.l_type.glyph());
+    }
+
+    // The player goes on top of anything sharing their spot.
+    place(current, PLAYER_GLYPH);
+
+    let border = format!("+{}+", "-".repeat(width));
+
+    let mut lines = vec![border.clone()];
+    lines.extend(grid.iter().map(|r| format!("|{}|", r.iter().collect::<String>())));
+    lines.push(border);
+
+    // Only list the kinds of places the player has actually found.
+    let mut legend = vec![format!("{} You ({})", PLAYER_GLYPH, current.name)];
+
+    for (_, l) in &locations {
+        let entry = format!("{} {}", l.l_type.glyph(), l.l_type.name());
+
+        if !legend.contains(&entry) {
+            legend.push(entry);
+        }
+    }
+
+    format!("{}\n\n{}", lines.join("\n"), legend.join("   "))
+}
@@ -1,16 +1,105 @@
 extern crate serde;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::common::{Condition, Named};
+use super::common::{Condition, Named, Reward};
 use super::data::GameData;
+use super::time::GameTime;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuestBranch {
+    pub stage: String,
+    pub conditions: Option<Vec<Condition>>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuestStage {
+    pub id: String,
+    pub journal: String,
+    pub conditions: Vec<Condition>,
+    pub rewards: Option<Vec<Reward>>,
+    /// Where to go once this stage is done. The first branch whose conditions pass is taken;
+    /// otherwise the quest moves on to the next stage in order.
+    pub branches: Option<Vec<QuestBranch>>,
+    /// Whether finishing this stage finishes the quest's stages, even if more follow it.
+    #[serde(default)]
+    pub end: bool
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TurnIn {
+    pub house: Option<String>,
+    /// The character's ID.
+    pub character: Option<String>
+}
+
+/// How a finished quest gets completed.
+/// Quests without one are only completed by a `Reward::Complete`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestCompletion {
+    Automatic,
+    TurnIn(TurnIn)
+}
+
+impl TurnIn {
+    pub fn matches(&self, house: Option<&String>, character: Option<&String>) -> bool {
+        let house = match (&self.house, house) {
+            (Some(h), Some(current)) => h == current,
+            (Some(_), None) => false,
+            (None, _) => true
+        };
+
+        let character = match (&self.character, character) {
+            (Some(c), Some(n)) => c == n,
+            (Some(_), None) => false,
+            (None, _) => true
+        };
+
+        house && character
+    }
+
+    pub fn display(&self, game: &GameData) -> String {
+        let house = self.house.as_ref().map(|h| game.houses.get(h).unwrap().name.clone());
+        let character = self.character.as_ref().map(|c| game.character(c).map_or(c, |c| &c.name));
+
+        match (character, house) {
+            (Some(c), Some(h)) => format!("Return to {} at the {}.", c, h),
+            (Some(c), None) => format!("Return to {}.", c),
+            (None, Some(h)) => format!("Return to the {}.", h),
+            (None, None) => String::new()
+        }
+    }
+}
+
+/// The player's progress through a staged quest.
+/// A `stage` of `None` means every stage along the way has been done.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct QuestProgress {
+    pub stage: Option<String>,
+    pub completed: Vec<String>
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Quest {
     pub name: String,
     #[serde(alias = "goal")]
     pub description: String,
-    pub requirements: Vec<Condition>
+    #[serde(default)]
+    pub requirements: Vec<Condition>,
+    #[serde(alias = "prereqs")]
+    pub prerequisites: Option<Vec<Condition>>,
+    pub stages: Option<Vec<QuestStage>>,
+    pub rewards: Option<Vec<Reward>>,
+    pub completion: Option<QuestCompletion>,
+    /// The quest fails as soon as any of these are met.
+    pub fail: Option<Vec<Condition>>,
+    pub deadline: Option<GameTime>,
+    /// How many minutes the player has to finish the quest once they take it on.
+    pub limit: Option<usize>,
+    /// Whether completing the quest takes the items its requirements ask for.
+    #[serde(default)]
+    pub consume: bool
 }
 
 impl Named for Quest {
@@ -20,18 +109,170 @@ impl Named for Quest {
 }
 
 impl Quest {
-    pub fn check(&self, game: &GameData) -> bool {
-        Condition::check_all(&self.requirements, game)
+    pub fn get_stage(&self, stage: &String) -> Option<&QuestStage> {
+        self.stages.as_ref()?
+            .iter()
+            .find(|s| &s.id == stage)
     }
 
-    pub fn display(&self, game: &GameData) -> String {
-        format!("{}\n\n{}\n\nRequirements:\n{}",
-            self.name,
-            self.description,
-            Condition::display_all(&self.requirements, game).iter()
-                .map(|c| format!("- {}", c))
-                .collect::<Vec<String>>()
-                .join("\n")
-        )
-    }
-}
\ No newline at end of file
+    /// The player's progress through this quest, starting at the first stage if they haven't begun.
+    pub fn progress(&self, id: &String, game: &GameData) -> Option<QuestProgress> {
+        let stages = self.stages.as_ref()?;
+
+        match game.global.player.quests.progress.get(id) {
+            Some(p) => Some(p.clone()),
+            None => Some(QuestProgress {
+                stage: stages.first().map(|s| s.id.clone()),
+                completed: Vec::new()
+            })
+        }
+    }
+
+    /// The stage the player is currently on, if this quest has stages left.
+    pub fn stage(&self, id: &String, game: &GameData) -> Option<&QuestStage> {
+        self.get_stage(&self.progress(id, game)?.stage?)
+    }
+
+    /// Where a finished stage leads, or `None` if it was the last one.
+    pub fn next(&self, stage: &QuestStage, game: &GameData) -> Option<String> {
+        if let Some(v) = &stage.branches {
+            let branch = v.iter().find(|b| match &b.conditions {
+                Some(c) => Condition::check_all(c, game),
+                None => true
+            });
+
+            if let Some(b) = branch {
+                return Some(b.stage.clone());
+            }
+        }
+
+        if stage.end {
+            return None;
+        }
+
+        let stages = self.stages.as_ref()?;
+        let position = stages.iter().position(|s| s.id == stage.id)?;
+
+        stages.get(position + 1).map(|s| s.id.clone())
+    }
+
+    pub fn check(&self, id: &String, game: &GameData) -> bool {
+        self.stage(id, game).is_none() && Condition::check_all(&self.requirements, game)
+    }
+
+    /// When the quest has to be finished by: the earlier of its deadline and the end of its time limit.
+    /// The time limit only counts once the player has taken the quest on.
+    pub fn due(&self, id: &String, game: &GameData) -> Option<GameTime> {
+        let limit = self.limit.and_then(|n| {
+            let mut t = game.global.player.quests.started.get(id)?.clone();
+            t.advance(n);
+
+            Some(t)
+        });
+
+        match (self.deadline.clone(), limit) {
+            (Some(a), Some(b)) => Some(if a.total() <= b.total() { a } else { b }),
+            (a, b) => a.or(b)
+        }
+    }
+
+    /// Whether the quest has passed its deadline or met any of its fail conditions.
+    pub fn failed(&self, id: &String, game: &GameData) -> bool {
+        let late = match self.due(id, game) {
+            Some(t) => game.global.time.total() > t.total(),
+            None => false
+        };
+
+        let failed = match &self.fail {
+            Some(v) => v.iter().any(|c| c.check(game)),
+            None => false
+        };
+
+        late || failed
+    }
+
+    /// Whether the player meets this quest's prerequisites, if it has any.
+    pub fn available(&self, game: &GameData) -> bool {
+        match &self.prerequisites {
+            Some(v) => Condition::check_all(v, game),
+            None => true
+        }
+    }
+
+    pub fn display_conditions(v: &[Condition], game: &GameData) -> String {
+        // Some conditions, like marks, are meant to stay hidden.
+        Condition::display_all(v, game).iter()
+            .filter(|c| !c.is_empty())
+            .map(|c| format!("- {}", c))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn display_stages(&self, id: &String, game: &GameData) -> Option<String> {
+        let stages = self.stages.as_ref()?;
+        let progress = self.progress(id, game)?;
+
+        let mut lines = progress.completed.iter()
+            .filter_map(|s| self.get_stage(s))
+            .map(|s| format!("✔ {}", s.journal))
+            .collect::<Vec<String>>();
+
+        if let Some(current) = progress.stage.as_ref().and_then(|s| self.get_stage(s)) {
+            lines.push(format!("→ {}", current.journal));
+
+            let conditions = Self::display_conditions(&current.conditions, game);
+
+            if !conditions.is_empty() {
+                lines.push(conditions);
+            }
+
+            // Stages the player hasn't reached are kept hidden.
+            let position = stages.iter().position(|s| s.id == current.id).unwrap();
+
+            for s in &stages[position + 1..] {
+                if !progress.completed.contains(&s.id) {
+                    lines.push(String::from("? ???"));
+                }
+            }
+        }
+
+        Some(lines.join("\n"))
+    }
+
+    pub fn display(&self, id: &String, game: &GameData) -> String {
+        let mut result = format!("{}\n\n{}", self.name, self.description);
+
+        if let Some(s) = self.display_stages(id, game) {
+            result.push_str(&format!("\n\nStages:\n{}", s));
+        }
+
+        if !self.requirements.is_empty() {
+            result.push_str(&format!("\n\nRequirements:\n{}", Self::display_conditions(&self.requirements, game)));
+        }
+
+        if let Some(t) = self.due(id, game) {
+            let day = match t.date(game) {
+                Some(d) => format!("the {}", d),
+                None => format!("day {}", t.day)
+            };
+
+            result.push_str(&format!("\n\nDeadline: {} on {}", t.display_time(), day));
+        }
+
+        if let Some(n) = self.limit {
+            if !game.global.player.quests.started.contains_key(id) {
+                result.push_str(&format!("\n\nTime limit: {} once taken on", GameTime::duration(n)));
+            }
+        }
+
+        if let Some(v) = &self.fail {
+            let conditions = Self::display_conditions(v, game);
+
+            if !conditions.is_empty() {
+                result.push_str(&format!("\n\nFails if any of:\n{}", conditions));
+            }
+        }
+
+        result
+    }
+}
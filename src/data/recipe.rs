@@ -0,0 +1,86 @@
+extern crate serde;
+
+use serde::Deserialize;
+
+use std::collections::HashMap;
+
+use super::common::{compare_map, Named};
+use super::data::GameData;
+use super::entity::PlayerStatus;
+use super::time::GameTime;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Recipe {
+    pub name: String,
+    #[serde(alias = "in")]
+    pub inputs: HashMap<String, usize>,
+    #[serde(alias = "out")]
+    pub outputs: HashMap<String, usize>,
+    pub skill: Option<String>,
+    pub class: Option<String>,
+    /// How long crafting takes, in minutes.
+    pub time: usize,
+    pub location: Option<String>,
+    pub house: Option<String>
+}
+
+impl Named for Recipe {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Recipe {
+    /// Whether the player is in the right place, knows how, and has the inputs.
+    pub fn craftable(&self, game: &GameData) -> bool {
+        let player = &game.global.player;
+
+        let skill = match &self.skill {
+            Some(s) => player.skills.contains(s),
+            None => true
+        };
+
+        let class = match &self.class {
+            Some(c) => &player.class == c,
+            None => true
+        };
+
+        let location = match &self.location {
+            Some(l) => &player.location == l,
+            None => true
+        };
+
+        let house = match (&self.house, &player.status) {
+            (Some(h), PlayerStatus::House(current)) => h == current,
+            (Some(_), _) => false,
+            (None, _) => true
+        };
+
+        skill && class && location && house && compare_map(&self.inputs, &player.inventory.counts())
+    }
+
+    /// The change in carried weight from crafting this recipe once.
+    pub fn weight(&self, game: &GameData) -> f64 {
+        let weigh = |m: &HashMap<String, usize>| m.iter()
+            .map(|(i, n)| game.items.get(i).unwrap().weight() * *n as f64)
+            .sum::<f64>();
+
+        weigh(&self.outputs) - weigh(&self.inputs)
+    }
+
+    pub fn display_items(m: &HashMap<String, usize>, game: &GameData) -> String {
+        m.iter()
+            .map(|(i, n)| format!("{} {}", n, game.items.get(i).unwrap().name))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    pub fn display(&self, game: &GameData) -> String {
+        format!("{} ({} into {}, taking {})",
+            self.name,
+            Self::display_items(&self.inputs, game),
+            Self::display_items(&self.outputs, game),
+            GameTime::duration(self.time)
+        )
+    }
+}
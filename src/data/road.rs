@@ -0,0 +1,234 @@
+extern crate rand;
+extern crate serde;
+
+use std::collections::HashMap;
+
+use rand::Rng;
+use serde::Deserialize;
+
+use super::data::GameData;
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Terrain {
+    #[default]
+    Road,
+    Plains,
+    Forest,
+    Hills,
+    Swamp,
+    Mountains
+}
+
+impl Terrain {
+    pub fn name(&self) -> &'static str {
+        use Terrain::*;
+
+        match self {
+            Road => "road",
+            Plains => "plains",
+            Forest => "forest",
+            Hills => "hills",
+            Swamp => "swamp",
+            Mountains => "mountains"
+        }
+    }
+
+    /// How much longer it takes to walk over this than over a road.
+    pub fn slowdown(&self) -> f64 {
+        use Terrain::*;
+
+        match self {
+            Road => 1.0,
+            Plains => 1.2,
+            Forest => 1.5,
+            Hills => 1.75,
+            Swamp => 2.0,
+            Mountains => 2.5
+        }
+    }
+}
+
+/// A connection to another location. Roads go both ways, so they only need to be written on one end.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Road {
+    pub to: String,
+    /// Defaults to the straight-line distance between the two locations.
+    pub distance: Option<f64>,
+    #[serde(default)]
+    pub terrain: Terrain,
+    /// The percent chance of running into trouble when walking this road.
+    #[serde(default)]
+    pub danger: u8,
+    /// Who the player runs into.
+    pub encounter: Option<HashMap<String, usize>>
+}
+
+/// One step of a route, from one location to the next.
+#[derive(Debug, Clone)]
+pub struct Leg {
+    pub from: String,
+    pub to: String,
+    pub distance: f64,
+    pub road: Option<Road>
+}
+
+impl Leg {
+    fn from(from: &String, road: &Road, game: &GameData) -> Self {
+        let a = &game.locations.get(from).unwrap().position;
+        let b = &game.locations.get(&road.to).unwrap().position;
+
+        Self {
+            from: from.clone(),
+            to: road.to.clone(),
+            distance: road.distance.unwrap_or_else(|| a.dist(b)),
+            road: Some(road.clone())
+        }
+    }
+
+    /// What it takes to walk this leg, in units of distance over a road.
+    pub fn cost(&self) -> f64 {
+        match &self.road {
+            Some(r) => self.distance * r.terrain.slowdown(),
+            None => self.distance
+        }
+    }
+
+    pub fn display(&self, game: &GameData) -> String {
+        let to = &game.locations.get(&self.to).unwrap().name;
+
+        match &self.road {
+            Some(r) => format!("- {} ({:.1}, {}{})",
+                to,
+                self.distance,
+                r.terrain.name(),
+                if r.danger > 0 { format!(", {}% danger", r.danger) } else { String::new() }
+            ),
+            None => format!("- {} ({:.1}, cross-country)", to, self.distance)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub legs: Vec<Leg>
+}
+
+impl Route {
+    /// Every road touching a location, including ones written on the other end.
+    fn roads(game: &GameData) -> HashMap<String, Vec<Road>> {
+        let mut result: HashMap<String, Vec<Road>> = HashMap::new();
+
+        for (id, l) in &game.locations {
+            for r in l.roads.iter().flatten() {
+                result.entry(id.clone()).or_default().push(r.clone());
+
+                let back = Road {
+                    to: id.clone(),
+                    ..r.clone()
+                };
+
+                result.entry(r.to.clone()).or_default().push(back);
+            }
+        }
+
+        result
+    }
+
+    /// The quickest way to walk between two locations along roads, using Dijkstra's algorithm.
+    /// If no roads connect them, the route goes straight there.
+    pub fn find(from: &String, to: &String, game: &GameData) -> Self {
+        let roads = Self::roads(game);
+
+        let mut costs: HashMap<String, f64> = HashMap::new();
+        let mut previous: HashMap<String, Leg> = HashMap::new();
+        let mut done: Vec<String> = Vec::new();
+
+        costs.insert(from.clone(), 0.0);
+
+        loop {
+            let next = costs.iter()
+                .filter(|(k, _)| !done.contains(k))
+                .min_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(k, v)| (k.clone(), *v));
+
+            let (current, cost) = match next {
+                Some(n) => n,
+                None => break
+            };
+
+            if &current == to {
+                break;
+            }
+
+            for r in roads.get(&current).into_iter().flatten() {
+                let leg = Leg::from(&current, r, game);
+                let total = cost + leg.cost();
+
+                if costs.get(&r.to).is_none_or(|c| total < *c) {
+                    costs.insert(r.to.clone(), total);
+                    previous.insert(r.to.clone(), leg);
+                }
+            }
+
+            done.push(current);
+        }
+
+        if from == to || !previous.contains_key(to) {
+            let a = &game.locations.get(from).unwrap().position;
+            let b = &game.locations.get(to).unwrap().position;
+
+            return Self {
+                legs: vec![Leg {
+                    from: from.clone(),
+                    to: to.clone(),
+                    distance: a.dist(b),
+                    road: None
+                }]
+            };
+        }
+
+        let mut legs = Vec::new();
+        let mut current = to.clone();
+
+        while &current != from {
+            let leg = previous.remove(&current).unwrap();
+            current = leg.from.clone();
+            legs.push(leg);
+        }
+
+        legs.reverse();
+
+        Self {
+            legs
+        }
+    }
+
+    pub fn distance(&self) -> f64 {
+        self.legs.iter().map(|l| l.distance).sum()
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.legs.iter().map(|l| l.cost()).sum()
+    }
+
+    /// Rolls each leg's danger, plus some extra, returning the first encounter the player runs into.
+    pub fn ambush(&self, extra: u8) -> Option<(&Leg, &HashMap<String, usize>)> {
+        let mut rng = rand::thread_rng();
+
+        self.legs.iter()
+            .filter_map(|l| {
+                let road = l.road.as_ref()?;
+                Some((l, road.encounter.as_ref()?, road.danger.saturating_add(extra)))
+            })
+            .find(|(_, _, danger)| rng.gen_range(0..100) < *danger)
+            .map(|(l, e, _)| (l, e))
+    }
+
+    pub fn display(&self, game: &GameData) -> String {
+        self.legs.iter()
+            .map(|l| l.display(game))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
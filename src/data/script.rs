@@ -0,0 +1,245 @@
+extern crate rhai;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+
+use super::common::Reward;
+use super::data::GameData;
+use super::journal::LogText;
+
+/// Changes a script asks for. Scripts can't touch the world directly;
+/// they queue up rewards through this, which are applied once the script finishes.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptChanges {
+    pub rewards: Vec<Reward>
+}
+
+impl ScriptChanges {
+    fn give(&mut self, item: &str, n: i64) {
+        let mut m = HashMap::new();
+        m.insert(item.to_string(), n as isize);
+
+        self.rewards.push(Reward::Items(m));
+    }
+
+    fn currency(&mut self, n: f64) {
+        self.rewards.push(Reward::Currency(n));
+    }
+
+    fn heal(&mut self, n: i64) {
+        self.rewards.push(Reward::Health(n as isize));
+    }
+
+    fn reputation(&mut self, location: &str, n: i64) {
+        let mut m = HashMap::new();
+        m.insert(location.to_string(), n as isize);
+
+        self.rewards.push(Reward::Reputation(m));
+    }
+
+    fn effect(&mut self, effect: &str, n: i64) {
+        let mut m = HashMap::new();
+        m.insert(effect.to_string(), n.max(0) as usize);
+
+        self.rewards.push(Reward::Effects(m));
+    }
+
+    fn mark(&mut self, mark: &str) {
+        self.rewards.push(Reward::Marks(vec![mark.to_string()]));
+    }
+
+    fn unmark(&mut self, mark: &str) {
+        self.rewards.push(Reward::RemoveMarks(vec![mark.to_string()]));
+    }
+
+    fn skill(&mut self, skill: &str) {
+        self.rewards.push(Reward::Skills(vec![skill.to_string()]));
+    }
+
+    fn log(&mut self, text: &str) {
+        self.rewards.push(Reward::Logs(vec![LogText::Plain(text.to_string())]));
+    }
+
+    fn assign(&mut self, quest: &str) {
+        self.rewards.push(Reward::Quests(vec![quest.to_string()]));
+    }
+
+    fn complete(&mut self, quest: &str) {
+        self.rewards.push(Reward::Complete(vec![quest.to_string()]));
+    }
+
+    fn fail(&mut self, quest: &str) {
+        self.rewards.push(Reward::Fail(vec![quest.to_string()]));
+    }
+}
+
+fn strings(v: &[String]) -> Dynamic {
+    v.iter()
+        .map(|s| Dynamic::from(s.clone()))
+        .collect::<Array>()
+        .into()
+}
+
+fn counts(m: &HashMap<String, usize>) -> Dynamic {
+    m.iter()
+        .map(|(k, v)| (k.as_str().into(), Dynamic::from(*v as i64)))
+        .collect::<Map>()
+        .into()
+}
+
+/// A read-only snapshot of the game that scripts see as `game`.
+fn view(game: &GameData) -> Map {
+    let player = &game.global.player;
+    let quests = &player.quests;
+
+    let mut p = Map::new();
+    p.insert("name".into(), player.name.clone().into());
+    p.insert("class".into(), player.class.clone().into());
+    p.insert("location".into(), player.location.clone().into());
+    p.insert("status".into(), player.status.name().into());
+    p.insert("health".into(), (player.vitality.health.value as i64).into());
+    p.insert("max_health".into(), (player.vitality.health.max as i64).into());
+    p.insert("currency".into(), player.inventory.currency.value.into());
+    p.insert("items".into(), counts(&player.inventory.counts()));
+    p.insert("reputation".into(), counts(&player.stats.reputation));
+    p.insert("defeated".into(), counts(&player.stats.defeated));
+    p.insert("skills".into(), strings(&player.skills));
+    p.insert("marks".into(), strings(player.stats.marks.as_deref().unwrap_or_default()));
+    p.insert("titles".into(), strings(player.stats.titles.as_deref().unwrap_or_default()));
+    p.insert("assigned".into(), strings(quests.assigned.as_deref().unwrap_or_default()));
+    p.insert("completed".into(), strings(quests.completed.as_deref().unwrap_or_default()));
+    p.insert("failed".into(), strings(quests.failed.as_deref().unwrap_or_default()));
+
+    let talked = game.global.characters.iter()
+        .map(|(k, c)| (k.clone(), c.talked))
+        .collect::<HashMap<String, usize>>();
+
+    p.insert("talked".into(), counts(&talked));
+
+    let time = &game.global.time;
+
+    let mut t = Map::new();
+    t.insert("day".into(), (time.day as i64).into());
+    t.insert("min".into(), (time.min as i64).into());
+    t.insert("hour".into(), (time.hrs() as i64).into());
+    t.insert("night".into(), time.is_night().into());
+
+    if let Some(d) = time.date(game) {
+        t.insert("year".into(), (d.year as i64).into());
+        t.insert("month".into(), d.month.name.clone().into());
+        t.insert("season".into(), d.month.season.clone().map_or(Dynamic::UNIT, |s| s.into()));
+        t.insert("weekday".into(), d.weekday.cloned().map_or(Dynamic::UNIT, |w| w.into()));
+    }
+
+    let mut result = Map::new();
+    result.insert("player".into(), p.into());
+    result.insert("time".into(), t.into());
+
+    result
+}
+
+/// Scripts from the `scripts` directory, compiled when the game loads.
+pub struct Scripts {
+    engine: Engine,
+    scripts: HashMap<String, AST>
+}
+
+impl fmt::Debug for Scripts {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list()
+            .entries(self.scripts.keys())
+            .finish()
+    }
+}
+
+impl Scripts {
+    pub const MAX_OPERATIONS: u64 = 100_000;
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+
+        // Keep content scripts from hanging the game or building huge values.
+        engine.set_max_operations(Self::MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(10_000)
+            .set_max_array_size(1_000)
+            .set_max_map_size(1_000)
+            .disable_symbol("eval");
+
+        engine.register_type_with_name::<ScriptChanges>("World")
+            .register_fn("give", ScriptChanges::give)
+            .register_fn("currency", ScriptChanges::currency)
+            .register_fn("heal", ScriptChanges::heal)
+            .register_fn("reputation", ScriptChanges::reputation)
+            .register_fn("effect", ScriptChanges::effect)
+            .register_fn("mark", ScriptChanges::mark)
+            .register_fn("unmark", ScriptChanges::unmark)
+            .register_fn("skill", ScriptChanges::skill)
+            .register_fn("log", ScriptChanges::log)
+            .register_fn("assign", ScriptChanges::assign)
+            .register_fn("complete", ScriptChanges::complete)
+            .register_fn("fail", ScriptChanges::fail);
+
+        engine
+    }
+
+    /// Compiles every script, erroring with the script's name if any fail to parse.
+    pub fn load(sources: BTreeMap<String, String>) -> Result<Self, String> {
+        let engine = Self::engine();
+        let mut scripts = HashMap::with_capacity(sources.len());
+
+        for (name, source) in sources {
+            let ast = engine.compile(&source)
+                .map_err(|e| format!("Error in script '{}': {}", name, e))?;
+
+            scripts.insert(name, ast);
+        }
+
+        Ok(Self {
+            engine,
+            scripts
+        })
+    }
+
+    /// Runs a script, returning its result and the changes it asked for.
+    pub fn eval(&self, id: &String, game: &GameData) -> Result<(Dynamic, Vec<Reward>), String> {
+        let ast = self.scripts.get(id)
+            .ok_or(format!("There's no script called '{}'.", id))?;
+
+        let mut scope = Scope::new();
+        scope.push_constant("game", view(game));
+        scope.push("world", ScriptChanges::default());
+
+        let result = self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+            .map_err(|e| format!("Error in script '{}': {}", id, e))?;
+
+        let changes = scope.get_value::<ScriptChanges>("world").unwrap_or_default();
+
+        Ok((result, changes.rewards))
+    }
+
+    /// Runs a script as a condition. Scripts that error or don't return `true` fail.
+    pub fn check(&self, id: &String, game: &GameData) -> bool {
+        match self.eval(id, game) {
+            Ok((result, _)) => result.as_bool().unwrap_or(false),
+            Err(e) => {
+                println!("{}", e);
+                false
+            }
+        }
+    }
+
+    /// Runs a script for its changes, along with any text it returns.
+    pub fn run(&self, id: &String, game: &GameData) -> (Option<String>, Vec<Reward>) {
+        match self.eval(id, game) {
+            Ok((result, rewards)) => (result.into_string().ok(), rewards),
+            Err(e) => {
+                println!("{}", e);
+                (None, Vec::new())
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+extern crate serde;
+extern crate serde_yaml;
+
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+
+use super::common::Condition;
+use super::data::GameData;
+use super::inventory::Currency;
+
+/// Pieces of a template: plain text, or the contents of a `{...}` tag.
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Tag(String)
+}
+
+/// Splits text into plain text and tags. Braces inside a tag are balanced,
+/// so conditions can use YAML flow syntax like `{?marks: {met: true}}`.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+
+    for c in text.chars() {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    if !current.is_empty() {
+                        tokens.push(Token::Text(current.clone()));
+                        current.clear();
+                    }
+                } else {
+                    current.push(c);
+                }
+
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+
+                if depth == 0 {
+                    tokens.push(Token::Tag(current.clone()));
+                    current.clear();
+                } else {
+                    current.push(c);
+                }
+            }
+            _ => current.push(c)
+        }
+    }
+
+    // An unclosed tag is just text.
+    if depth > 0 {
+        current.insert(0, '{');
+    }
+
+    if !current.is_empty() {
+        tokens.push(Token::Text(current));
+    }
+
+    tokens
+}
+
+/// Looks up a single variable, or returns `None` if it isn't one.
+fn variable(tag: &str, game: &GameData) -> Option<String> {
+    let player = &game.global.player;
+
+    let value = match tag {
+        "player.name" => player.name.clone(),
+        "player.class" => game.classes.get(&player.class)?.name.clone(),
+        "player.titles" => player.stats.titles.as_ref()?.join(", "),
+        "time" => game.global.time.display_time(),
+        "day" => game.global.time.day.to_string(),
+        "date" => game.global.time.date(game)?.to_string(),
+        "currency" => Currency::display(player.inventory.currency.value, game),
+        "location.name" => game.location().name.clone(),
+        _ => {
+            if let Some(id) = tag.strip_prefix("item.") {
+                game.items.get(id)?.name.clone()
+            } else if let Some(n) = tag.strip_prefix("currency:") {
+                Currency::words(n.trim().parse::<f64>().ok()?, game)
+            } else {
+                return None;
+            }
+        }
+    };
+
+    Some(value)
+}
+
+/// Renders tokens until the end of input, or one of the `stops`: an else (`{|}`) or end (`{/}`) tag.
+/// Returns the rendered text and the tag it stopped at. Control tags that don't close anything are left as text.
+fn render_tokens(tokens: &[Token], i: &mut usize, stops: &[&str], game: &GameData) -> (String, Option<String>) {
+    let mut result = String::new();
+
+    while *i < tokens.len() {
+        let token = &tokens[*i];
+        *i += 1;
+
+        match token {
+            Token::Text(s) => result.push_str(s),
+            Token::Tag(t) if stops.contains(&t.as_str()) => return (result, Some(t.clone())),
+            Token::Tag(t) if t.starts_with('?') => {
+                let check = match serde_yaml::from_str::<Condition>(&t[1..]) {
+                    Ok(c) => c.check(game),
+                    Err(_) => false
+                };
+
+                let (yes, stop) = render_tokens(tokens, i, &["|", "/"], game);
+
+                let no = if stop.as_deref() == Some("|") {
+                    render_tokens(tokens, i, &["/"], game).0
+                } else {
+                    String::new()
+                };
+
+                result.push_str(if check { &yes } else { &no });
+            }
+            Token::Tag(t) => match variable(t.trim(), game) {
+                Some(s) => result.push_str(&s),
+                None => result.push_str(&format!("{{{}}}", t))
+            }
+        }
+    }
+
+    (result, None)
+}
+
+/// Fills in variables and conditional segments in some display text.
+pub fn render(text: &str, game: &GameData) -> String {
+    if !text.contains('{') {
+        return String::from(text);
+    }
+
+    render_tokens(&tokenize(text), &mut 0, &[], game).0
+}
+
+/// Checks that every condition in some display text parses.
+pub fn check(text: &str) -> Result<(), String> {
+    for token in tokenize(text) {
+        if let Token::Tag(t) = token {
+            if let Some(c) = t.strip_prefix('?') {
+                if let Err(e) = serde_yaml::from_str::<Condition>(c) {
+                    return Err(format!("Invalid condition '{}' in '{}': {}", c, text, e));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Deserializes display text, rejecting any with conditions that don't parse.
+pub fn checked<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    let text = String::deserialize(d)?;
+    check(&text).map_err(D::Error::custom)?;
+
+    Ok(text)
+}
+
+pub fn checked_all<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<String>, D::Error> {
+    let v = Vec::<String>::deserialize(d)?;
+
+    for text in &v {
+        check(text).map_err(D::Error::custom)?;
+    }
+
+    Ok(v)
+}
+
+pub fn checked_option<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<String>>, D::Error> {
+    match Option::<Vec<String>>::deserialize(d)? {
+        Some(v) => {
+            for text in &v {
+                check(text).map_err(D::Error::custom)?;
+            }
+
+            Ok(Some(v))
+        }
+        None => Ok(None)
+    }
+}
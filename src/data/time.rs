@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 
 use std::fmt::Display;
 
+use super::data::GameData;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GameTime {
     pub day: usize,
@@ -28,6 +30,11 @@ impl GameTime {
         }
     }
 
+    /// Minutes since the start of the first day.
+    pub fn total(&self) -> usize {
+        self.day * Self::DAY_INTERVAL + self.min
+    }
+
     pub fn hrs(&self) -> usize {
         self.min / Self::HR_INTERVAL
     }
@@ -66,13 +73,157 @@ impl GameTime {
         } else {
             format!("{} days, {} hours, and {} minutes",
                 d / Self::DAY_INTERVAL,
-                d / Self::HR_INTERVAL,
+                d % Self::DAY_INTERVAL / Self::HR_INTERVAL,
                 d % Self::HR_INTERVAL
             )
         }
     }
 }
 
+/// A span of hours in the day. Spans where `from` is later than `to` wrap past midnight.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Hours {
+    pub from: usize,
+    pub to: usize
+}
+
+impl Hours {
+    pub fn contains(&self, time: &GameTime) -> bool {
+        let hr = time.hrs();
+
+        if self.from <= self.to {
+            hr >= self.from && hr < self.to
+        } else {
+            hr >= self.from || hr < self.to
+        }
+    }
+
+    pub fn display_hour(hr: usize) -> String {
+        GameTime { day: 0, min: hr * GameTime::HR_INTERVAL }.display_time()
+    }
+}
+
+impl Display for Hours {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "{} to {}", Self::display_hour(self.from), Self::display_hour(self.to))
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Month {
+    pub name: String,
+    pub days: usize,
+    pub season: Option<String>
+}
+
+/// The in-world date of the first day.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StartDate {
+    pub year: usize,
+    /// Which month, counting from 1.
+    pub month: usize,
+    pub day: usize,
+    /// Which weekday, counting from 1.
+    pub weekday: Option<usize>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Calendar {
+    pub weekdays: Vec<String>,
+    pub months: Vec<Month>,
+    pub start: StartDate
+}
+
+/// A day of the calendar.
+#[derive(Debug, Clone)]
+pub struct Date<'a> {
+    pub year: usize,
+    pub month: &'a Month,
+    pub day: usize,
+    pub weekday: Option<&'a String>
+}
+
+impl Calendar {
+    pub fn date(&self, time: &GameTime) -> Date<'_> {
+        let year_length = self.months.iter().map(|m| m.days).sum::<usize>().max(1);
+
+        // Days into the starting year the journey began, plus however many have gone by since.
+        let start = self.months.iter()
+            .take(self.start.month.saturating_sub(1))
+            .map(|m| m.days)
+            .sum::<usize>() + self.start.day.saturating_sub(1);
+
+        let passed = time.day.saturating_sub(1);
+        let total = start + passed;
+
+        let mut day = total % year_length;
+        let mut month = &self.months[0];
+
+        for m in &self.months {
+            month = m;
+
+            if day < m.days {
+                break;
+            }
+
+            day -= m.days;
+        }
+
+        let weekday = match self.weekdays.len() {
+            0 => None,
+            n => self.weekdays.get((self.start.weekday.unwrap_or(1).saturating_sub(1) + passed) % n)
+        };
+
+        Date {
+            year: self.start.year + total / year_length,
+            month,
+            day: day + 1,
+            weekday
+        }
+    }
+}
+
+impl<'a> Date<'a> {
+    pub fn ordinal(n: usize) -> String {
+        let suffix = match (n % 10, n % 100) {
+            (_, 11..=13) => "th",
+            (1, _) => "st",
+            (2, _) => "nd",
+            (3, _) => "rd",
+            _ => "th"
+        };
+
+        format!("{}{}", n, suffix)
+    }
+}
+
+impl<'a> Display for Date<'a> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "{} of {}, Year {}", Self::ordinal(self.day), self.month.name, self.year)
+    }
+}
+
+impl GameTime {
+    /// The in-world date, if the game has a calendar.
+    pub fn date<'a>(&self, game: &'a GameData) -> Option<Date<'a>> {
+        game.config.calendar.as_ref()
+            .filter(|c| !c.months.is_empty())
+            .map(|c| c.date(self))
+    }
+
+    /// Like the plain display, but with the in-world date when there's a calendar.
+    pub fn display(&self, game: &GameData) -> String {
+        match self.date(game) {
+            Some(d) => format!("The time is {}. It is {}the {}.",
+                self.display_time(),
+                d.weekday.map(|w| format!("{}, ", w)).unwrap_or_default(),
+                d
+            ),
+            None => self.to_string()
+        }
+    }
+}
+
 impl Display for GameTime {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         write!(fmt, "The time is {}. It is day {} of your journey.",
@@ -73,6 +73,15 @@ impl Filesystem {
         Ok(result)
     }
 
+    /// Reads a directory that a game doesn't have to include, treating a missing one as empty.
+    pub fn read_optional_dir(&self, path: &str) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+        if !self.get(path).exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        self.read_dir(path)
+    }
+
     /// Returns the filename of a directory as an `Option<String>`.
     /// Note that Rust only supports UTF-8 characters in Strings.
     pub fn get_file_name(d: &DirEntry) -> Option<String> {
@@ -25,6 +25,11 @@ impl InputController {
         }
     }
 
+    /// The terminal's width, if output is going to one.
+    pub fn columns(&mut self) -> Option<usize> {
+        self.rl.dimensions().map(|d| d.0)
+    }
+
     pub fn prompt(&mut self, text: String) -> Option<String> {
         println!("{}", text);
     
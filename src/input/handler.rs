@@ -1,12 +1,12 @@
 use super::super::data::data::GameData;
 use super::super::data::entity::PlayerStatus;
-use super::super::data::location::LocationType;
+use super::super::data::journal::JournalEntry;
+use super::super::data::map;
 
 use super::args::Args;
 use super::controller::InputController;
 
 pub fn handler(game: &mut GameData, input: &mut InputController) {
-    use LocationType::*;
     use PlayerStatus::*;
     
     let mut exiting = false;
@@ -29,23 +29,56 @@ pub fn handler(game: &mut GameData, input: &mut InputController) {
                         game.cycle_combat();
 
                         match cmd {
+                            "attack" => {
+                                game.attack(input);
+
+                                Ok(())
+                            }
+                            _ => e
+                        }
+                    }
+                    Dungeon(_) => {
+                        match cmd {
+                            "go" => {
+                                if args.check(1) {
+                                    game.go(&args.input);
+                                } else {
+                                    println!("You need to provide a direction.");
+                                }
+
+                                Ok(())
+                            }
+                            "look" => {
+                                println!("{}", game.look());
+
+                                Ok(())
+                            }
+                            "search" => {
+                                game.search();
+
+                                Ok(())
+                            }
+                            "leave" => {
+                                game.global.player.status = PlayerStatus::Location;
+
+                                println!("You made your way out of {}.", game.location().name);
+
+                                Ok(())
+                            }
                             _ => e
                         }
                     }
-                    House(_) => {
-                        let house = game.house().unwrap().clone();
+                    House(h) => {
+                        let residents = game.residents(h);
 
                         match cmd {
                             "talk" => {
-                                match input.choice("Who will you talk to?", house.list(), "You decided not to talk to anyone.") {
-                                    Some(d) => {
-                                        match house.talk(d.1, game, input) {
-                                            Some(s) => println!("{}", s),
-                                            None => ()
-                                        }
-                                    }
-                                    None => ()
-                                }   
+                                game.talk(residents, input);
+
+                                Ok(())
+                            }
+                            "craft" => {
+                                game.craft(input);
 
                                 Ok(())
                             }
@@ -76,15 +109,49 @@ pub fn handler(game: &mut GameData, input: &mut InputController) {
                                 Ok(())
                             }
                             "visit" => {
-                                use LocationType::*;
+                                game.visit(input);
+
+                                Ok(())
+                            }
+                            "talk" => {
+                                let locals = game.locals(&game.global.player.location);
+                                game.talk(locals, input);
+
+                                Ok(())
+                            }
+                            "craft" => {
+                                game.craft(input);
+
+                                Ok(())
+                            }
+                            "repair" => {
+                                match game.location().shops() {
+                                    Some(_) => {
+                                        game.repair(input);
 
-                                match game.location().l_type {
-                                    Town(_) | City(_) | Capital(_) => {
-                                        game.visit(input);
+                                        Ok(())
+                                    }
+                                    None => e
+                                }
+                            }
+                            "audience" | "palace" => {
+                                match game.location().palace() {
+                                    Some(_) => {
+                                        game.audience(input);
 
                                         Ok(())
                                     }
-                                    _ => e
+                                    None => e
+                                }
+                            }
+                            "board" => {
+                                match game.location().tavern() {
+                                    Some(_) => {
+                                        game.board(input);
+
+                                        Ok(())
+                                    }
+                                    None => e
                                 }
                             }
                             _ => e
@@ -102,13 +169,22 @@ pub fn handler(game: &mut GameData, input: &mut InputController) {
                     Err(e) => {
                         match cmd {
                             "dbg" => println!("\n{:#?}", game),
-                            "inv" => println!("\n{}", game.global.player.inventory.display_line(game)),
-                            "log" => println!("\n{}", game.global.player.stats.log(false)),
-                            "recent" => println!("\n{}", game.global.player.stats.log(true)),
+                            "inv" => {
+                                let inventory = &game.global.player.inventory;
+
+                                println!("\n{}\n\n{}", inventory.display_line(game), inventory.display_equipped(game));
+
+                                if let Some(n) = game.capacity() {
+                                    println!("Carrying {:.1} of {:.1}.", inventory.weight(game), n);
+                                }
+                            }
+                            "equip" => game.equip(input),
+                            "log" => println!("\n{}", JournalEntry::query(&args.list, game)),
+                            "recent" => println!("\n{}", JournalEntry::recent(game)),
                             "quest" => {
                                 if args.check(1) {
                                     match game.match_best(&args.input, &game.quests) {
-                                        Some(q) => println!("\n{}", q.1.display(game)),
+                                        Some(q) => println!("\n{}", q.1.display(&q.0, game)),
                                         None => println!("That's not a valid quest.")
                                     }
                                 } else {
@@ -116,11 +192,40 @@ pub fn handler(game: &mut GameData, input: &mut InputController) {
                                 }
                             }
                             "quests" => println!("\n{}", game.quest_book()),
+                            "where" => {
+                                if args.check(1) {
+                                    println!("{}", game.whereabouts(&args.input));
+                                } else {
+                                    println!("You need to provide someone to look for.");
+                                }
+                            }
+                            "map" => {
+                                let columns = input.columns();
+
+                                match args.list.first().map(|s| s.parse::<f64>()) {
+                                    Some(Ok(z)) if z >= 1.0 => println!("\n{}", map::render(game, Some(z), columns)),
+                                    Some(_) => println!("The zoom needs to be a number of at least 1."),
+                                    None => println!("\n{}", map::render(game, None, columns))
+                                }
+                            }
+                            "abandon" => {
+                                if args.check(1) {
+                                    match game.match_best(&args.input, &game.quests) {
+                                        Some(q) => game.abandon(&q.0, input),
+                                        None => println!("That's not a valid quest.")
+                                    }
+                                } else {
+                                    println!("You need to provide a quest.");
+                                }
+                            }
                             _ => println!("{}", e)
                         }
                     }
                 }
 
+                game.update_events();
+                game.update_quests();
+
                 println!();
             },
             None => {